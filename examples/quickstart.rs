@@ -12,20 +12,24 @@ async fn main() -> Result<()> {
         .with_env_filter(EnvFilter::from_default_env())
         .init();
 
+    // Fail on a missing API key now rather than after the browser is up
+    let cua_cfg = CuaConfig::default();
+    cua_cfg.validate()?;
+
     let computer = if let Ok(ws) = std::env::var("CHROME_WS_URL") {
-        if !ws.trim().is_empty() { ChromiumComputer::connect(&ws).await? } else { ChromiumComputer::launch(BrowserConfig { headless: false, user_agent: None }).await? }
+        if !ws.trim().is_empty() { ChromiumComputer::connect(&ws).await? } else { ChromiumComputer::launch(BrowserConfig { headless: false, ..Default::default() }).await? }
     } else {
-        ChromiumComputer::launch(BrowserConfig { headless: false, user_agent: None }).await?
+        ChromiumComputer::launch(BrowserConfig { headless: false, ..Default::default() }).await?
     };
-    let cua = CuaClient::new(CuaConfig { ..Default::default() })?;
+    let cua = CuaClient::new(cua_cfg)?;
     let reasoner = CuaReasoner::with_config(
         cua,
         "Proceed without asking for confirmations. Complete the task end-to-end.",
-        glass_hands::agent::CuaReasonerConfig { stop_on_message: false, auto_confirm_text: Some("Yes, proceed and download the invoice PDF.".to_string()) }
+        glass_hands::agent::CuaReasonerConfig { stop_on_message: false, auto_confirm_text: Some("Yes, proceed and download the invoice PDF.".to_string()), ..Default::default() }
     );
     let runs_dir = std::env::temp_dir().join("glass_hands_runs");
     let store = Arc::new(DiskSnapshotStore::new(runs_dir.clone()));
-    let agent = Agent::with_defaults(computer, reasoner, AgentConfig { max_steps: 40, step_timeout: Duration::from_millis(3000), scopes: vec![] })
+    let agent = Agent::with_defaults(computer, reasoner, AgentConfig { max_steps: 40, step_timeout: Duration::from_millis(3000), ..Default::default() })
         .with_snapshot_store(store)
         .with_artifacts_dir(runs_dir.clone());
 
@@ -34,6 +38,8 @@ async fn main() -> Result<()> {
         "Go to OpenAI Billing. Open the invoice labeled 'Paid $900.09 Aug 25, 2025'. Follow redirects in the same tab and download the PDF.",
         Some("https://platform.openai.com"),
     ).await?;
+    println!("{}", report.to_markdown());
+    agent.close().await?;
 
     Ok(())
 }
@@ -0,0 +1,625 @@
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use crate::agent::{Action, ActionResult, AgentError, AssertKind, Computer, DomNode, Locator, Snapshot};
+use crate::browser::{Browser, BrowserError, ClickCheck, PdfOptions};
+
+/// Cap on nodes returned by `find_all`, to keep huge pages from flooding the reasoner.
+const FIND_ALL_LIMIT: usize = 100;
+/// Tab presses `Action::TabTo` tries before giving up.
+const TAB_TO_LIMIT: usize = 100;
+/// Cap on interactive elements listed by `observe`.
+const OBSERVE_LIMIT: usize = 200;
+/// How long a click target that went stale gets to match again before the one retry.
+const STALE_RETRY_WAIT: Duration = Duration::from_millis(1000);
+
+/// `Computer` logic shared by every adapter that drives a `Browser`.
+pub(crate) struct BrowserComputer {
+    browser: Browser,
+    cache: Mutex<CaptureCache>,
+    pub(crate) min_screenshot_interval: Duration, // coalesce snapshot() captures closer together than this
+}
+
+/// The most recent screenshot, reused when none is required or one was taken moments ago.
+#[derive(Default)]
+struct CaptureCache {
+    image: Option<String>,
+    url: Option<String>,   // page URL when `image` was taken
+    at: Option<Instant>,
+    stale: bool,           // an action changed the page since `image` was taken
+}
+
+impl BrowserComputer {
+    pub(crate) fn new(browser: Browser) -> Self {
+        Self { browser, cache: Mutex::new(CaptureCache::default()), min_screenshot_interval: Duration::ZERO }
+    }
+
+    pub(crate) fn with_min_screenshot_interval(mut self, interval: Duration) -> Self {
+        self.min_screenshot_interval = interval;
+        self
+    }
+
+    pub(crate) fn browser(&self) -> &Browser {
+        &self.browser
+    }
+
+    async fn capture(&self, url: &str) -> Result<String, AgentError> {
+        let snap_b64 = self
+            .browser
+            .screenshot_b64()
+            .await
+            .map_err(|e| AgentError::Other(e.to_string()))?;
+        self.remember(url, &snap_b64).await;
+        Ok(snap_b64)
+    }
+
+    /// Like `capture`, but returns the previous image if it is younger than
+    /// `min_screenshot_interval` and the page has neither changed nor moved since.
+    async fn capture_coalesced(&self, url: &str) -> Result<String, AgentError> {
+        {
+            let cache = self.cache.lock().await;
+            if let (Some(image), Some(at)) = (&cache.image, cache.at) {
+                if !cache.stale && cache.url.as_deref() == Some(url) && at.elapsed() < self.min_screenshot_interval {
+                    return Ok(image.clone());
+                }
+            }
+        }
+        self.capture(url).await
+    }
+
+    async fn remember(&self, url: &str, image: &str) {
+        *self.cache.lock().await = CaptureCache {
+            image: Some(image.to_string()),
+            url: Some(url.to_string()),
+            at: Some(Instant::now()),
+            stale: false,
+        };
+    }
+}
+
+#[async_trait]
+impl Computer for BrowserComputer {
+    async fn open_url(&self, url: &str) -> Result<Snapshot, AgentError> {
+        navigate(&self.browser, url).await?;
+        // Report where redirects landed, not what was requested
+        let final_url = self.browser.url().await.unwrap_or_else(|_| url.to_string());
+        let snap_b64 = self.capture(&final_url).await?;
+        let mut snap = Snapshot::new(Some(final_url), Some(snap_b64));
+        page_state(&self.browser, &mut snap).await;
+        Ok(snap)
+    }
+
+    async fn snapshot(&self) -> Result<Snapshot, AgentError> {
+        let url = self
+            .browser
+            .url()
+            .await
+            .map_err(|e| AgentError::Other(e.to_string()))?;
+        let snap_b64 = self.capture_coalesced(&url).await?;
+        let mut snap = Snapshot::new(Some(url), Some(snap_b64));
+        snap.console = self.browser.drain_console().await;
+        page_state(&self.browser, &mut snap).await;
+        Ok(snap)
+    }
+
+    async fn observe(&self) -> Result<Snapshot, AgentError> {
+        let url = self
+            .browser
+            .url()
+            .await
+            .map_err(|e| AgentError::Other(e.to_string()))?;
+        let elements = self
+            .browser
+            .interactive_elements(OBSERVE_LIMIT)
+            .await
+            .map_err(|e| AgentError::Other(e.to_string()))?;
+        // Never coalesced: the image must show the page the ref ids (and any marks) were taken from
+        let snap_b64 = self.capture(&url).await?;
+        let mut snap = Snapshot::new(Some(url), Some(snap_b64));
+        snap.console = self.browser.drain_console().await;
+        snap.elements = elements;
+        page_state(&self.browser, &mut snap).await;
+        Ok(snap)
+    }
+
+    async fn dismiss_consent(&self) -> Result<bool, AgentError> {
+        let dismissed = self
+            .browser
+            .dismiss_common_banners()
+            .await
+            .map_err(|e| AgentError::Other(e.to_string()))?;
+        if dismissed {
+            self.cache.lock().await.stale = true;
+        }
+        Ok(dismissed)
+    }
+
+    async fn viewport(&self) -> Result<(u32, u32), AgentError> {
+        self.browser
+            .viewport()
+            .await
+            .map_err(|e| AgentError::Other(e.to_string()))
+    }
+
+    async fn close(&self) -> Result<(), AgentError> {
+        self.browser
+            .close()
+            .await
+            .map_err(|e| AgentError::Other(e.to_string()))
+    }
+
+    async fn get_text(&self, css: &str) -> Result<String, AgentError> {
+        self.browser
+            .get_text(css)
+            .await
+            .map_err(|e| AgentError::Other(e.to_string()))
+    }
+
+    async fn get_attribute(&self, css: &str, name: &str) -> Result<Option<String>, AgentError> {
+        self.browser
+            .get_attribute(css, name)
+            .await
+            .map_err(|e| AgentError::Other(e.to_string()))
+    }
+
+    async fn snapshot_light(&self) -> Result<Snapshot, AgentError> {
+        let url = self
+            .browser
+            .url()
+            .await
+            .map_err(|e| AgentError::Other(e.to_string()))?;
+        let mut snap = Snapshot::new(Some(url), None);
+        snap.title = self.browser.title().await.ok();
+        page_state(&self.browser, &mut snap).await;
+        Ok(snap)
+    }
+
+    async fn find(&self, locator: &Locator, timeout: Duration) -> Result<DomNode, AgentError> {
+        self.browser
+            .wait_for_element(locator, timeout)
+            .await
+            .map_err(|e| AgentError::Other(e.to_string()))?
+            .ok_or_else(|| AgentError::Timeout(format!("{:?} not found within {}ms", locator, timeout.as_millis())))
+    }
+
+    async fn find_all(&self, locator: &Locator, timeout: Duration) -> Result<Vec<DomNode>, AgentError> {
+        let Locator::Css { selector } = locator else {
+            return Ok(vec![self.find(locator, timeout).await?]);
+        };
+        tokio::time::timeout(timeout, self.browser.query_all(selector, FIND_ALL_LIMIT))
+            .await
+            .map_err(|_| AgentError::Timeout(format!("find_all exceeded {}ms", timeout.as_millis())))?
+            .map_err(|e| AgentError::Other(e.to_string()))
+    }
+
+    async fn highlight(&self, locator: &Locator) -> Result<(), AgentError> {
+        let res = match locator {
+            Locator::Coordinates { x, y } => {
+                self.browser
+                    .highlight_rect(*x as f64 - 10.0, *y as f64 - 10.0, 20.0, 20.0)
+                    .await
+            }
+            Locator::Css { selector } => self.browser.highlight(selector).await,
+            _ => return Ok(()),
+        };
+        res.map_err(|e| AgentError::Other(e.to_string()))
+    }
+
+    async fn act(&self, action: &Action, timeout: Duration) -> Result<ActionResult, AgentError> {
+        let out = dispatch_action(&self.browser, action, timeout).await?;
+        if let (Some(url), Some(image)) = (&out.snapshot.url, &out.snapshot.image_base64) {
+            self.remember(url, image).await;
+        }
+        Ok(out)
+    }
+
+    async fn act_without_screenshot(&self, action: &Action, timeout: Duration) -> Result<ActionResult, AgentError> {
+        let done = perform_with_timeout(&self.browser, action, timeout).await?;
+        if done.changed {
+            settle(&self.browser, action).await;
+            follow_tabs(&self.browser).await;
+        }
+        // No image: the last one shows the page before this action, and a reasoner that needs
+        // one for a call output keeps its own copy
+        let snapshot = self.snapshot_light().await?;
+        self.cache.lock().await.stale |= done.changed;
+        Ok(ActionResult {
+            snapshot,
+            changed: done.changed,
+            message: done.message,
+            hit_target: done.hit,
+        })
+    }
+}
+
+/// Perform `action` on `browser` within `timeout`, let the page settle, then capture a
+/// fresh snapshot. Reusable by any adapter or harness that drives a `Browser`.
+pub async fn dispatch_action(browser: &Browser, action: &Action, timeout: Duration) -> Result<ActionResult, AgentError> {
+    let done = perform_with_timeout(browser, action, timeout).await?;
+    if done.changed {
+        settle(browser, action).await;
+        follow_tabs(browser).await;
+    }
+    let url = browser
+        .url()
+        .await
+        .map_err(|e| AgentError::Other(e.to_string()))?;
+    let snap_b64 = browser
+        .screenshot_b64()
+        .await
+        .map_err(|e| AgentError::Other(e.to_string()))?;
+    let mut snapshot = Snapshot::new(Some(url), Some(snap_b64));
+    // Whatever the page logged while reacting often explains a click that "did nothing"
+    snapshot.console = browser.drain_console().await;
+    page_state(browser, &mut snapshot).await;
+    Ok(ActionResult {
+        snapshot,
+        changed: done.changed,
+        message: done.message,
+        hit_target: done.hit,
+    })
+}
+
+/// Fill in the snapshot's scroll offset and load state; best-effort, left unset on failure.
+async fn page_state(browser: &Browser, snap: &mut Snapshot) {
+    snap.scroll = browser.scroll_position().await.ok();
+    snap.ready_state = browser.ready_state().await.ok();
+    if let Some(form) = browser.snapshot_form() {
+        // The form may not be on every page of a flow; leave the field empty there
+        snap.form_state = browser.form_state(form).await.ok();
+    }
+}
+
+/// Click `target` at its `click_point`. If the element went stale between resolving and
+/// clicking (an SPA re-render replaced or detached it), wait for the locator to match again
+/// and retry once instead of failing the step. Ref ids are only re-assigned by the next
+/// `observe`, so a stale `Locator::Ref` fails straight away.
+async fn click_element(browser: &Browser, target: &Locator, button: &str) -> anyhow::Result<()> {
+    let attempt = || async {
+        let (x, y) = browser.click_point(target).await?;
+        browser.click(x.round() as i64, y.round() as i64, button).await
+    };
+    match attempt().await {
+        Err(e) if is_stale(&e) && !matches!(target, Locator::Ref { .. }) => {
+            tracing::debug!(?target, "click target went stale, re-resolving: {}", e);
+            browser.wait_for_element(target, STALE_RETRY_WAIT).await?;
+            attempt().await
+        }
+        res => res,
+    }
+}
+
+/// Whether an element operation failed because the node vanished rather than for good.
+fn is_stale(e: &anyhow::Error) -> bool {
+    matches!(e.downcast_ref::<BrowserError>(), Some(BrowserError::ElementGone { .. }))
+}
+
+/// Without single-tab mode, move to a tab the action opened (or back from one it closed), so
+/// the snapshot shows where the interaction went.
+async fn follow_tabs(browser: &Browser) {
+    if browser.single_tab_mode() || !browser.owns_page() {
+        return;
+    }
+    if let Err(e) = browser.follow_tabs().await {
+        tracing::warn!("tab tracking failed: {}", e);
+    }
+}
+
+/// What `perform_action` did.
+struct Performed {
+    changed: bool,           // false for read-only actions such as `Assert`
+    message: Option<String>, // e.g. the assertion outcome or the navigation's HTTP status
+    hit: Option<String>,     // element under a coordinate click, per `BrowserConfig::click_check`
+}
+
+async fn perform_with_timeout(browser: &Browser, action: &Action, timeout: Duration) -> Result<Performed, AgentError> {
+    tokio::time::timeout(timeout, perform_action(browser, action, timeout))
+        .await
+        .map_err(|_| AgentError::Timeout(format!("action exceeded {}ms", timeout.as_millis())))?
+}
+
+/// Give the page time to react per the browser's `SettleConfig`: actions that may load a
+/// page wait for the network to go quiet, the rest get a short fixed delay.
+async fn settle(browser: &Browser, action: &Action) {
+    let cfg = browser.settle_config();
+    match action {
+        Action::NavGoto { .. }
+        | Action::NavBack
+        | Action::NavForward
+        | Action::Reload
+        | Action::Submit { .. }
+        | Action::Click { .. }
+        | Action::ClickInView { .. }
+        | Action::WaitForUrl { .. }
+        | Action::WaitForResponse { .. } => {
+            match browser.wait_for_network_idle(cfg.network_idle, cfg.network_idle_timeout).await {
+                Ok(true) => {}
+                Ok(false) => tracing::debug!("page still busy after {}ms", cfg.network_idle_timeout.as_millis()),
+                Err(e) => tracing::warn!("network idle wait failed: {}", e),
+            }
+        }
+        _ => tokio::time::sleep(cfg.interaction_delay).await,
+    }
+}
+
+/// Navigate and return the HTTP status of the final response, when known.
+async fn navigate(browser: &Browser, url: &str) -> Result<Option<i64>, AgentError> {
+    let status = browser
+        .goto_with_status(url)
+        .await
+        .map_err(|e| AgentError::Other(e.to_string()))?;
+    // Ensure links open in same tab to keep control
+    if browser.single_tab_mode() {
+        let _ = browser.enable_single_tab_mode().await;
+    }
+    browser
+        .wait_for_stable()
+        .await
+        .map_err(|e| AgentError::Other(e.to_string()))?;
+    Ok(status)
+}
+
+/// Map an `Action` onto `Browser` calls.
+async fn perform_action(browser: &Browser, action: &Action, timeout: Duration) -> Result<Performed, AgentError> {
+    // A response wait accepts what the action before it triggered, but nothing older
+    if !matches!(action, Action::WaitForResponse { .. }) {
+        browser.mark_action_start().await;
+    }
+    // Give late-rendered targets the step budget to appear rather than failing on first look.
+    // Asserts check presence themselves; coordinates and the focused-element sentinel need no element.
+    if let Some(target) = action.target() {
+        let waits = !matches!(action, Action::Assert { .. })
+            && !matches!(target, Locator::Coordinates { .. })
+            && !matches!(target, Locator::Css { selector } if selector == "*");
+        if waits {
+            // Slightly under the step budget so the clearer error wins the race
+            let budget = timeout.saturating_sub(Duration::from_millis(50));
+            let found = browser
+                .wait_for_element(target, budget)
+                .await
+                .map_err(|e| AgentError::Other(e.to_string()))?;
+            if found.is_none() {
+                return Err(AgentError::Timeout(format!("{:?} not found within {}ms", target, budget.as_millis())));
+            }
+        }
+    }
+    let mut hit = None; // element under a coordinate click, reported once the tail below has run
+    match action {
+        Action::WaitForUrl { contains } => {
+            // Slightly under the step budget so the clearer error wins the race
+            let budget = timeout.saturating_sub(Duration::from_millis(50));
+            let url = browser
+                .wait_for_url(|u| u.contains(contains.as_str()), budget)
+                .await
+                .map_err(|e| AgentError::Timeout(e.to_string()))?;
+            return Ok(Performed { changed: true, message: Some(format!("url: {}", url)), hit: None });
+        }
+        Action::WaitForResponse { url_contains } => {
+            let budget = timeout.saturating_sub(Duration::from_millis(50));
+            let status = browser
+                .wait_for_response_since(url_contains, browser.last_action_mark(), budget)
+                .await
+                .map_err(|e| AgentError::Timeout(e.to_string()))?;
+            return Ok(Performed { changed: true, message: Some(format!("HTTP {}", status)), hit: None });
+        }
+        Action::Assert { locator, condition } => {
+            let message = check_assertion(browser, locator, condition).await?;
+            return Ok(Performed { changed: false, message: Some(message), hit: None });
+        }
+        Action::NavGoto { url } => {
+            let status = navigate(browser, url).await?;
+            return Ok(Performed { changed: true, message: status.map(|s| format!("HTTP {}", s)), hit: None });
+        }
+        Action::Click { target, button } => {
+            let button = button.as_deref().unwrap_or("left");
+            match target {
+                Locator::Coordinates { x, y } => {
+                    let (x, y) = (*x as i64, *y as i64);
+                    hit = match browser.click_check() {
+                        ClickCheck::Off => None,
+                        check => match browser.element_at(x, y).await {
+                            Ok(Some((desc, dead))) => {
+                                if dead && check == ClickCheck::RejectDeadZone {
+                                    return Err(AgentError::Computer(format!(
+                                        "nothing clickable at ({}, {}), only {}",
+                                        x, y, desc
+                                    )));
+                                }
+                                Some(desc)
+                            }
+                            Ok(None) => Some("(outside viewport)".to_string()),
+                            Err(e) => {
+                                tracing::warn!("click target probe failed: {}", e);
+                                None
+                            }
+                        },
+                    };
+                    browser
+                        .click(x, y, button)
+                        .await
+                        .map_err(|e| AgentError::Other(e.to_string()))?;
+                }
+                _ => {
+                    click_element(browser, target, button)
+                        .await
+                        .map_err(|e| AgentError::Other(e.to_string()))?;
+                }
+            }
+        }
+        Action::Hover { target } => {
+            match target {
+                Locator::Coordinates { x, y } => {
+                    browser
+                        .move_mouse(*x as i64, *y as i64)
+                        .await
+                        .map_err(|e| AgentError::Other(e.to_string()))?;
+                }
+                _ => {
+                    let (x, y) = browser
+                        .scroll_to_center(target)
+                        .await
+                        .map_err(|e| AgentError::Other(e.to_string()))?;
+                    browser
+                        .move_mouse(x.round() as i64, y.round() as i64)
+                        .await
+                        .map_err(|e| AgentError::Other(e.to_string()))?;
+                }
+            }
+        }
+        Action::Scroll { target: None, dx, dy } => {
+            browser
+                .scroll(*dx as i64, *dy as i64)
+                .await
+                .map_err(|e| AgentError::Other(e.to_string()))?;
+        }
+        Action::Key { combo } => {
+            // Named keys go through trusted CDP events; synthetic JS events don't submit forms
+            let res = if crate::browser::cdp_key(combo).is_some() {
+                browser.press_key_cdp(combo).await
+            } else {
+                browser.keypress(combo).await
+            };
+            res.map_err(|e| AgentError::Other(e.to_string()))?;
+        }
+        Action::TabTo { until } => {
+            let reached = browser
+                .tab_to(until, TAB_TO_LIMIT)
+                .await
+                .map_err(|e| AgentError::Other(e.to_string()))?;
+            if !reached {
+                return Err(AgentError::Computer(format!("{:?} not reached within {} tabs", until, TAB_TO_LIMIT)));
+            }
+        }
+        Action::SavePdf { path } => {
+            let pdf = browser
+                .print_to_pdf(PdfOptions::default())
+                .await
+                .map_err(|e| AgentError::Other(e.to_string()))?;
+            let path = PathBuf::from(path);
+            if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+                tokio::fs::create_dir_all(dir).await.map_err(|e| AgentError::Other(e.to_string()))?;
+            }
+            tokio::fs::write(&path, &pdf).await.map_err(|e| AgentError::Other(e.to_string()))?;
+            return Ok(Performed { changed: false, message: Some(format!("saved {} bytes to {}", pdf.len(), path.display())), hit: None });
+        }
+        Action::KeyHold { combo, hold_ms } => {
+            browser
+                .hold_key(combo, Duration::from_millis(*hold_ms))
+                .await
+                .map_err(|e| AgentError::Other(e.to_string()))?;
+        }
+        Action::NavBack | Action::NavForward => {
+            let delta = if matches!(action, Action::NavBack) { -1 } else { 1 };
+            browser
+                .history_go(delta)
+                .await
+                .map_err(|e| AgentError::Other(e.to_string()))?;
+        }
+        Action::Reload => {
+            browser
+                .reload()
+                .await
+                .map_err(|e| AgentError::Other(e.to_string()))?;
+        }
+        Action::ClickInView { target } => {
+            click_element(browser, target, "left")
+                .await
+                .map_err(|e| AgentError::Other(e.to_string()))?;
+        }
+        Action::Type { text, into, clear } => {
+            // "*" means whatever already has focus (how CUA typing is mapped)
+            let focused_already = matches!(into, Locator::Css { selector } if selector == "*");
+            let res = if focused_already {
+                browser.type_text(text).await
+            } else {
+                browser.type_into(into, text, *clear).await
+            };
+            res.map_err(|e| AgentError::Other(e.to_string()))?;
+        }
+        Action::SetNetwork { offline, latency_ms, download_bps, upload_bps } => {
+            browser
+                .set_network_conditions(*offline, *latency_ms, *download_bps, *upload_bps)
+                .await
+                .map_err(|e| AgentError::Other(e.to_string()))?;
+        }
+        Action::SelectOption { target, value, label, index } => {
+            browser
+                .select_option(target, value.as_deref(), label.as_deref(), *index)
+                .await
+                .map_err(|e| AgentError::Other(e.to_string()))?;
+        }
+        Action::SetValue { target, value } => {
+            browser
+                .set_value(target, value)
+                .await
+                .map_err(|e| AgentError::Other(e.to_string()))?;
+        }
+        Action::SetChecked { target, checked } => {
+            browser
+                .set_checked(target, *checked)
+                .await
+                .map_err(|e| AgentError::Other(e.to_string()))?;
+        }
+        Action::FileUpload { target, path } => {
+            browser
+                .set_file_inputs(&[(target.clone(), vec![PathBuf::from(path)])])
+                .await
+                .map_err(|e| AgentError::Other(e.to_string()))?;
+        }
+        Action::FileUploadMulti { inputs } => {
+            let inputs: Vec<_> = inputs
+                .iter()
+                .map(|(target, paths)| (target.clone(), paths.iter().map(PathBuf::from).collect()))
+                .collect();
+            browser
+                .set_file_inputs(&inputs)
+                .await
+                .map_err(|e| AgentError::Other(e.to_string()))?;
+        }
+        _ => {
+            return Err(AgentError::Other(
+                "action not implemented in chromium adapter".into(),
+            ));
+        }
+    }
+    // Keep to same tab post-action as actions might trigger new tabs
+    if browser.single_tab_mode() {
+        let _ = browser.enable_single_tab_mode().await;
+    }
+    Ok(Performed { changed: true, message: None, hit })
+}
+
+async fn check_assertion(browser: &Browser, locator: &Locator, condition: &AssertKind) -> Result<String, AgentError> {
+    let map = |e: anyhow::Error| AgentError::Other(e.to_string());
+    let (passed, what) = match condition {
+        AssertKind::Exists => (browser.exists(locator).await.map_err(map)?, format!("{:?} exists", locator)),
+        AssertKind::NotExists => (!browser.exists(locator).await.map_err(map)?, format!("{:?} does not exist", locator)),
+        AssertKind::TextContains(text) => (
+            browser.text_contains(locator, text).await.map_err(map)?,
+            format!("{:?} text contains {:?}", locator, text),
+        ),
+        AssertKind::UrlMatches(pattern) => {
+            let url = browser.url().await.map_err(map)?;
+            (url.contains(pattern.as_str()), format!("url {:?} matches {:?}", url, pattern))
+        }
+    };
+    if passed {
+        Ok(format!("assert passed: {}", what))
+    } else {
+        Err(AgentError::Computer(format!("assert failed: {}", what)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn staleness_comes_from_the_error_type_not_its_text() {
+        let gone = BrowserError::ElementGone { locator: "Ref { id: \"3\" }".into(), reason: "not found" };
+        assert!(is_stale(&anyhow::Error::new(gone)));
+        assert!(!is_stale(&anyhow::anyhow!("element not found: #submit")));
+        assert!(!is_stale(&anyhow::Error::new(BrowserError::NoDisplay)));
+    }
+}
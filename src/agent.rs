@@ -1,13 +1,15 @@
 use async_trait::async_trait;
 use nanoid::nanoid;
 use serde::{Deserialize, Serialize};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 use tracing::{info, warn};
+use crate::adapter::BrowserComputer;
 use crate::browser::Browser;
-use crate::cua::{CuaAction, CuaClient, CuaOutput, CuaToolImage, ResponseId};
-use serde_json::Value;
-use tokio::sync::Mutex;
+pub use crate::dom::{DomNode, DomRect, InteractiveElement, Locator};
+use crate::cua::{CallOutput, CuaAction, CuaCall, CuaClient, CuaOutput, CuaToolImage, History, ResponseId, SafetyCheck, TokenUsage};
+use tokio::sync::{Mutex, Notify};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::fs as async_fs;
@@ -20,43 +22,149 @@ use base64::Engine as _;
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum Action {
-    Click { target: Locator },
-    Type { text: String, into: Locator },
+    Click {
+        target: Locator,
+        #[serde(default)]
+        button: Option<String>, // "left" (default), "right" or "middle"
+    },
+    ClickInView { target: Locator }, // scroll the target to the viewport center, then click it
+    Type {
+        text: String,
+        into: Locator,
+        #[serde(default)]
+        clear: bool, // replace the field's current value instead of appending to it
+    },
+    SetValue { target: Locator, value: String }, // assign an input's value directly; no keystrokes
     Key { combo: String },
+    KeyHold { combo: String, hold_ms: u64 }, // key down, hold, key up (games, canvases)
+    TabTo { until: Locator }, // press Tab until the element has keyboard focus
+    SavePdf { path: String }, // render the page to PDF at this path relative to the run's artifacts dir
     Hover { target: Locator },
     Scroll { target: Option<Locator>, dx: i32, dy: i32 },
     Drag { from: Locator, to: Locator },
     NavGoto { url: String },
+    NavBack,
+    NavForward,
+    Reload,
+    WaitForUrl { contains: String }, // block until the page URL contains this, e.g. after a redirect
+    WaitForResponse { url_contains: String }, // block until a network response with this in its URL arrives
     Submit { target: Locator },
     FileUpload { target: Locator, path: String },
+    FileUploadMulti { inputs: Vec<(Locator, Vec<String>)> }, // several file inputs in one step
     ClipboardRead,
     ClipboardWrite { data: String },
+    SetNetwork { offline: bool, latency_ms: u64, download_bps: f64, upload_bps: f64 },
+    SelectOption { target: Locator, value: Option<String>, label: Option<String>, index: Option<usize> },
+    SetChecked { target: Locator, checked: bool },
+    Assert { locator: Locator, condition: AssertKind },
 }
 
+/// Condition checked by `Action::Assert`; failure surfaces as `AgentError::Computer`.
 #[derive(Clone, Debug, Serialize, Deserialize)]
-#[serde(tag = "by", rename_all = "snake_case")]
-pub enum Locator {
-    Css { selector: String },
-    XPath { expr: String },
-    Text { pattern: String },
-    Id { id: String },
-    Aria { role: Option<String>, name: Option<String> },
-    Coordinates { x: i32, y: i32 },
+#[serde(rename_all = "snake_case")]
+pub enum AssertKind {
+    Exists,
+    NotExists,
+    TextContains(String), // the element's rendered text contains this
+    UrlMatches(String),   // the current URL contains this; the locator is ignored
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct DomRect {
-    pub x: f64,
-    pub y: f64,
-    pub width: f64,
-    pub height: f64,
+/// Fieldless discriminant of `Action`, for filtering (`AgentConfig::allowed_actions`) and
+/// counting. Serializes like `Action`'s `type` tag, e.g. "nav_goto".
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionKind {
+    Click,
+    ClickInView,
+    Type,
+    SetValue,
+    Key,
+    KeyHold,
+    TabTo,
+    SavePdf,
+    Hover,
+    Scroll,
+    Drag,
+    NavGoto,
+    NavBack,
+    NavForward,
+    Reload,
+    WaitForUrl,
+    WaitForResponse,
+    Submit,
+    FileUpload,
+    FileUploadMulti,
+    ClipboardRead,
+    ClipboardWrite,
+    SetNetwork,
+    SelectOption,
+    SetChecked,
+    Assert,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct DomNode {
-    pub locator: Locator,
-    pub description: Option<String>,
-    pub rect: Option<DomRect>,
+impl Action {
+    pub fn kind(&self) -> ActionKind {
+        match self {
+            Action::Click { .. } => ActionKind::Click,
+            Action::ClickInView { .. } => ActionKind::ClickInView,
+            Action::Type { .. } => ActionKind::Type,
+            Action::SetValue { .. } => ActionKind::SetValue,
+            Action::Key { .. } => ActionKind::Key,
+            Action::KeyHold { .. } => ActionKind::KeyHold,
+            Action::TabTo { .. } => ActionKind::TabTo,
+            Action::SavePdf { .. } => ActionKind::SavePdf,
+            Action::Hover { .. } => ActionKind::Hover,
+            Action::Scroll { .. } => ActionKind::Scroll,
+            Action::Drag { .. } => ActionKind::Drag,
+            Action::NavGoto { .. } => ActionKind::NavGoto,
+            Action::NavBack => ActionKind::NavBack,
+            Action::NavForward => ActionKind::NavForward,
+            Action::Reload => ActionKind::Reload,
+            Action::WaitForUrl { .. } => ActionKind::WaitForUrl,
+            Action::WaitForResponse { .. } => ActionKind::WaitForResponse,
+            Action::Submit { .. } => ActionKind::Submit,
+            Action::FileUpload { .. } => ActionKind::FileUpload,
+            Action::FileUploadMulti { .. } => ActionKind::FileUploadMulti,
+            Action::ClipboardRead => ActionKind::ClipboardRead,
+            Action::ClipboardWrite { .. } => ActionKind::ClipboardWrite,
+            Action::SetNetwork { .. } => ActionKind::SetNetwork,
+            Action::SelectOption { .. } => ActionKind::SelectOption,
+            Action::SetChecked { .. } => ActionKind::SetChecked,
+            Action::Assert { .. } => ActionKind::Assert,
+        }
+    }
+
+    /// The element the action operates on, if any.
+    pub fn target(&self) -> Option<&Locator> {
+        match self {
+            Action::Click { target, .. }
+            | Action::ClickInView { target }
+            | Action::Hover { target }
+            | Action::Submit { target }
+            | Action::FileUpload { target, .. }
+            | Action::SelectOption { target, .. }
+            | Action::SetChecked { target, .. }
+            | Action::SetValue { target, .. }
+            | Action::Assert { locator: target, .. } => Some(target),
+            Action::TabTo { until } => Some(until),
+            Action::Type { into, .. } => Some(into),
+            Action::Scroll { target, .. } => target.as_ref(),
+            Action::Drag { from, .. } => Some(from),
+            Action::FileUploadMulti { inputs } => inputs.first().map(|(target, _)| target),
+            Action::Key { .. }
+            | Action::KeyHold { .. }
+            | Action::NavGoto { .. }
+            | Action::NavBack
+            | Action::NavForward
+            | Action::Reload
+            | Action::WaitForUrl { .. }
+            | Action::WaitForResponse { .. }
+            | Action::SavePdf { .. }
+            | Action::ClipboardRead
+            | Action::ClipboardWrite { .. }
+            | Action::SetNetwork { .. } => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -66,7 +174,74 @@ pub struct Snapshot {
     pub title: Option<String>,
     pub image_base64: Option<String>,
     pub dom_summary: Option<String>,
-    pub captured_at_ms: u128,
+    pub captured_at_ms: u128, // Unix epoch millis when the snapshot was taken
+    #[serde(default)]
+    pub console: Vec<String>, // page console output and JS errors since the previous snapshot
+    #[serde(default)]
+    pub elements: Vec<InteractiveElement>, // filled by `Computer::observe`
+    #[serde(default)]
+    pub scroll: Option<(i64, i64)>, // page scroll offset (x, y) in CSS pixels, when known
+    #[serde(default)]
+    pub ready_state: Option<String>, // `document.readyState` at capture time, when known
+    #[serde(default)]
+    pub form_state: Option<HashMap<String, String>>, // field values of `BrowserConfig::snapshot_form`
+}
+
+impl Snapshot {
+    /// Create a snapshot with a fresh id, stamped with the current wall-clock time.
+    pub fn new(url: Option<String>, image_base64: Option<String>) -> Self {
+        Self {
+            id: nanoid!(),
+            url,
+            title: None,
+            image_base64,
+            dom_summary: None,
+            captured_at_ms: now_ms(),
+            console: Vec::new(),
+            elements: Vec::new(),
+            scroll: None,
+            ready_state: None,
+            form_state: None,
+        }
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn with_dom_summary(mut self, dom_summary: impl Into<String>) -> Self {
+        self.dom_summary = Some(dom_summary.into());
+        self
+    }
+
+    /// Whether two snapshots show the same page state: equal url, title and DOM summary, and
+    /// screenshots that are byte-identical or share a perceptual hash. `id`, `captured_at_ms`,
+    /// console output and elements are ignored. The hash tolerates re-encoding noise, so tiny
+    /// changes (a single typed character) can compare equal.
+    pub fn content_eq(&self, other: &Snapshot) -> bool {
+        if self.url != other.url || self.title != other.title || self.dom_summary != other.dom_summary {
+            return false;
+        }
+        match (&self.image_base64, &other.image_base64) {
+            (None, None) => true,
+            (Some(a), Some(b)) if a == b => true,
+            (Some(_), Some(_)) => matches!((self.image_hash(), other.image_hash()), (Some(a), Some(b)) if a == b),
+            _ => false,
+        }
+    }
+
+    /// Whether the page was still loading its document when captured; false when unknown.
+    pub fn is_loading(&self) -> bool {
+        self.ready_state.as_deref() == Some("loading")
+    }
+
+    /// Perceptual (difference) hash of the screenshot; None without a decodable PNG.
+    pub fn image_hash(&self) -> Option<u64> {
+        let png = B64.decode(self.image_base64.as_deref()?).ok()?;
+        let img = crate::imaging::decode_png(&png).ok()?;
+        Some(crate::imaging::dhash(&img))
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -74,12 +249,16 @@ pub struct ActionResult {
     pub snapshot: Snapshot,
     pub changed: bool,
     pub message: Option<String>,
+    #[serde(default)]
+    pub hit_target: Option<String>, // element under a coordinate click, when the computer probes for it
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct Memory {
     pub run_id: String,
     pub notes: Vec<String>,
+    #[serde(default)]
+    pub hint: Option<String>, // operator nudge for the next `think` only, see `Agent::hint`
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -96,6 +275,29 @@ pub struct Thought {
     pub plan: String,
     pub action: Option<Action>,
     pub rationale: Option<String>,
+    /// Raw model action that could not be translated into an `Action`. The loop is generic over
+    /// reasoners, so this is how it tells an unsupported action from a deliberate no-op think
+    /// and enforces `AgentConfig::max_unmapped`.
+    #[serde(default)]
+    pub unmapped_action: Option<String>,
+    #[serde(default = "default_true")]
+    pub requires_screenshot: bool, // whether the reasoner needs a fresh image after this step
+}
+
+impl Default for Thought {
+    fn default() -> Self {
+        Self {
+            plan: String::new(),
+            action: None,
+            rationale: None,
+            unmapped_action: None,
+            requires_screenshot: true,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -124,6 +326,8 @@ pub enum AgentError {
     Denied(Scope),
     #[error("timeout: {0}")]
     Timeout(String),
+    #[error("model refused: {0}")]
+    Refused(String),
     #[error("memory error: {0}")]
     Memory(String),
     #[error("other error: {0}")]
@@ -142,6 +346,34 @@ pub struct RunMetrics {
     pub steps: usize,
     pub time_ms: u128,
     pub success: bool,
+    #[serde(default)]
+    pub by_kind: HashMap<ActionKind, usize>, // every executed action, by kind
+}
+
+impl RunMetrics {
+    /// Count one executed action of `kind`.
+    pub fn record(&mut self, kind: ActionKind) {
+        *self.by_kind.entry(kind).or_default() += 1;
+    }
+
+    /// `Click` and `ClickInView` actions executed.
+    pub fn clicks(&self) -> usize {
+        self.count(&[ActionKind::Click, ActionKind::ClickInView])
+    }
+
+    /// `Type` and `SetValue` actions executed.
+    pub fn types(&self) -> usize {
+        self.count(&[ActionKind::Type, ActionKind::SetValue])
+    }
+
+    /// `NavGoto`, `NavBack`, `NavForward` and `Reload` actions executed.
+    pub fn navigations(&self) -> usize {
+        self.count(&[ActionKind::NavGoto, ActionKind::NavBack, ActionKind::NavForward, ActionKind::Reload])
+    }
+
+    fn count(&self, kinds: &[ActionKind]) -> usize {
+        kinds.iter().filter_map(|k| self.by_kind.get(k)).sum()
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
@@ -154,6 +386,8 @@ pub struct StepLog {
     pub snapshot_id: Option<String>,
     pub error: Option<String>,
     pub timestamp_ms: u128,
+    #[serde(default)]
+    pub hit_target: Option<String>, // what was actually under the cursor for a coordinate click
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -167,6 +401,56 @@ pub struct RunReport {
     pub error: Option<String>,
 }
 
+impl RunReport {
+    pub fn to_json_pretty(&self) -> Result<String, AgentError> {
+        serde_json::to_string_pretty(self).map_err(|e| AgentError::Other(e.to_string()))
+    }
+
+    pub fn to_json_compact(&self) -> Result<String, AgentError> {
+        serde_json::to_string(self).map_err(|e| AgentError::Other(e.to_string()))
+    }
+
+    /// Human-readable summary: goal, status, metrics and one table row per step.
+    pub fn to_markdown(&self) -> String {
+        // Keep cell text on one line and from closing the cell early
+        let cell = |s: &str| s.replace('|', "\\|").replace('\n', " ");
+        let mut md = format!("# Run {}\n\n", self.run_id);
+        md.push_str(&format!("**Goal:** {}\n\n", cell(&self.goal.task)));
+        md.push_str(&format!("**Status:** {:?}", self.status));
+        if let Some(err) = &self.error {
+            md.push_str(&format!(" ({})", cell(err)));
+        }
+        md.push_str(&format!(
+            "\n\n**Steps:** {} | **Time:** {} ms | **Success:** {}\n\n",
+            self.metrics.steps, self.metrics.time_ms, self.metrics.success
+        ));
+        if !self.metrics.by_kind.is_empty() {
+            // Most frequent first, so pathological patterns (30 scrolls) stand out
+            let mut kinds: Vec<_> = self.metrics.by_kind.iter().collect();
+            kinds.sort_by(|a, b| b.1.cmp(a.1).then_with(|| format!("{:?}", a.0).cmp(&format!("{:?}", b.0))));
+            let kinds: Vec<String> = kinds.iter().map(|(k, n)| format!("{:?} {}", k, n)).collect();
+            md.push_str(&format!("**Actions:** {}\n\n", kinds.join(", ")));
+        }
+        md.push_str("| Step | Result | Action | Plan | Error |\n|---|---|---|---|---|\n");
+        for s in &self.steps {
+            let action = s
+                .action
+                .as_ref()
+                .and_then(|a| serde_json::to_string(a).ok())
+                .unwrap_or_default();
+            md.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                s.step,
+                cell(&s.result_hint),
+                cell(&action),
+                cell(&s.plan),
+                cell(s.error.as_deref().unwrap_or("")),
+            ));
+        }
+        md
+    }
+}
+
 // ========================= Pluggable Subsystems =========================
 
 #[async_trait]
@@ -174,7 +458,145 @@ pub trait Computer: Send + Sync {
     async fn open_url(&self, url: &str) -> Result<Snapshot, AgentError>;
     async fn snapshot(&self) -> Result<Snapshot, AgentError>;
     async fn find(&self, locator: &Locator, timeout: Duration) -> Result<DomNode, AgentError>;
+
+    /// Every element the locator matches, in document order. Defaults to `find`'s single node.
+    async fn find_all(&self, locator: &Locator, timeout: Duration) -> Result<Vec<DomNode>, AgentError> {
+        Ok(vec![self.find(locator, timeout).await?])
+    }
+
     async fn act(&self, action: &Action, timeout: Duration) -> Result<ActionResult, AgentError>;
+
+    /// Like `act`, for when no fresh image is needed: adapters that can skip the capture return
+    /// a lightweight snapshot (url, title, page state) without an image. Defaults to `act`.
+    async fn act_without_screenshot(&self, action: &Action, timeout: Duration) -> Result<ActionResult, AgentError> {
+        self.act(action, timeout).await
+    }
+
+    /// `snapshot` plus the page's interactive elements, each with a ref id a reasoner can act
+    /// on via `Locator::Ref`. Adapters without element access return a plain snapshot.
+    async fn observe(&self) -> Result<Snapshot, AgentError> {
+        self.snapshot().await
+    }
+
+    /// Page state without a screenshot (url/title only), for steps that need no image.
+    async fn snapshot_light(&self) -> Result<Snapshot, AgentError> {
+        self.snapshot().await
+    }
+
+    /// Click away a cookie/consent banner if one is showing; returns whether one was.
+    /// Adapters that cannot detect banners report none.
+    async fn dismiss_consent(&self) -> Result<bool, AgentError> {
+        Ok(false)
+    }
+
+    /// Viewport size in CSS pixels, the space coordinate locators live in. Defaults to the
+    /// CUA tool display (1280x800) for adapters that cannot measure it.
+    async fn viewport(&self) -> Result<(u32, u32), AgentError> {
+        Ok((1280, 800))
+    }
+
+    /// Visually mark a target for debugging. Adapters without an overlay can ignore it.
+    async fn highlight(&self, _locator: &Locator) -> Result<(), AgentError> {
+        Ok(())
+    }
+
+    /// Release what the computer owns (browser process, temp dirs). Not usable afterwards.
+    async fn close(&self) -> Result<(), AgentError> {
+        Ok(())
+    }
+
+    /// Whether snapshot ids from this computer must be kept as they are, e.g. because they
+    /// come from a recording. Otherwise the agent re-ids every snapshot per
+    /// `AgentConfig::id_len`/`id_alphabet`.
+    fn keeps_snapshot_ids(&self) -> bool {
+        false
+    }
+
+    /// Rendered text of the first element matching `css`; errors if nothing matches.
+    async fn get_text(&self, _css: &str) -> Result<String, AgentError> {
+        Err(AgentError::Other("get_text not implemented by this computer".into()))
+    }
+
+    /// Attribute `name` of the first element matching `css` (None if it lacks the attribute);
+    /// errors if nothing matches.
+    async fn get_attribute(&self, _css: &str, _name: &str) -> Result<Option<String>, AgentError> {
+        Err(AgentError::Other("get_attribute not implemented by this computer".into()))
+    }
+}
+
+/// `impl Computer for $ty` passing every method through to `self.$field`. Methods written in
+/// the optional trailing block replace the pass-through (only `act` and
+/// `act_without_screenshot` may be given there).
+macro_rules! forward_computer {
+    ([$($gen:tt)*] $ty:ty, $field:ident) => {
+        forward_computer!([$($gen)*] $ty, $field, {
+            async fn act(&self, action: &Action, timeout: Duration) -> Result<ActionResult, AgentError> {
+                self.$field.act(action, timeout).await
+            }
+
+            async fn act_without_screenshot(&self, action: &Action, timeout: Duration) -> Result<ActionResult, AgentError> {
+                self.$field.act_without_screenshot(action, timeout).await
+            }
+        });
+    };
+    ([$($gen:tt)*] $ty:ty, $field:ident, { $($own:tt)* }) => {
+        #[async_trait]
+        impl<$($gen)*> Computer for $ty {
+            $($own)*
+
+            async fn open_url(&self, url: &str) -> Result<Snapshot, AgentError> {
+                self.$field.open_url(url).await
+            }
+
+            async fn snapshot(&self) -> Result<Snapshot, AgentError> {
+                self.$field.snapshot().await
+            }
+
+            async fn observe(&self) -> Result<Snapshot, AgentError> {
+                self.$field.observe().await
+            }
+
+            async fn snapshot_light(&self) -> Result<Snapshot, AgentError> {
+                self.$field.snapshot_light().await
+            }
+
+            fn keeps_snapshot_ids(&self) -> bool {
+                self.$field.keeps_snapshot_ids()
+            }
+
+            async fn viewport(&self) -> Result<(u32, u32), AgentError> {
+                self.$field.viewport().await
+            }
+
+            async fn dismiss_consent(&self) -> Result<bool, AgentError> {
+                self.$field.dismiss_consent().await
+            }
+
+            async fn find(&self, locator: &Locator, timeout: Duration) -> Result<DomNode, AgentError> {
+                self.$field.find(locator, timeout).await
+            }
+
+            async fn find_all(&self, locator: &Locator, timeout: Duration) -> Result<Vec<DomNode>, AgentError> {
+                self.$field.find_all(locator, timeout).await
+            }
+
+            async fn highlight(&self, locator: &Locator) -> Result<(), AgentError> {
+                self.$field.highlight(locator).await
+            }
+
+            async fn get_text(&self, css: &str) -> Result<String, AgentError> {
+                self.$field.get_text(css).await
+            }
+
+            async fn get_attribute(&self, css: &str, name: &str) -> Result<Option<String>, AgentError> {
+                self.$field.get_attribute(css, name).await
+            }
+
+            async fn close(&self) -> Result<(), AgentError> {
+                self.$field.close().await
+            }
+        }
+    };
 }
 
 #[async_trait]
@@ -193,6 +615,42 @@ pub trait Reasoner: Send + Sync {
         snapshot: &Snapshot,
         memory: &Memory,
     ) -> Result<bool, AgentError>;
+
+    /// Re-check a success before the run ends on it (`AgentConfig::verify_success`), given a
+    /// snapshot taken just now. Defaults to `success`; reasoners whose `success` only reads
+    /// their own state should look at the page again instead.
+    async fn confirm_success(
+        &self,
+        goal: &Goal,
+        snapshot: &Snapshot,
+        memory: &Memory,
+    ) -> Result<bool, AgentError> {
+        self.success(goal, snapshot, memory).await
+    }
+
+    /// One or more thoughts to act on in order before the reasoner is consulted again (neither
+    /// `think` nor `success` is called until all have run). Reasoners that can plan several
+    /// deterministic actions at once override this to save round-trips; the default is `think`.
+    async fn think_batch(
+        &self,
+        goal: &Goal,
+        memory: &Memory,
+        snapshot: &Snapshot,
+        last_error: Option<&AgentError>,
+    ) -> Result<Vec<Thought>, AgentError> {
+        Ok(vec![self.think(goal, memory, snapshot, last_error).await?])
+    }
+
+    /// Tokens consumed so far, for reasoners backed by a metered API; `None` when untracked.
+    async fn usage(&self) -> Option<TokenUsage> {
+        None
+    }
+
+    /// Called as a run starts: pick up state saved under `run_id` by an earlier, interrupted
+    /// attempt (see `Agent::run_goal_with_id`). Returns whether anything was resumed.
+    async fn resume(&self, _run_id: &str) -> Result<bool, AgentError> {
+        Ok(false)
+    }
 }
 
 #[async_trait]
@@ -200,11 +658,49 @@ pub trait MemoryStore: Send + Sync {
     async fn write_run_start(&self, run_id: &str, goal: &Goal) -> Result<(), AgentError>;
     async fn write_step(&self, run_id: &str, step: &StepLog) -> Result<(), AgentError>;
     async fn write_run_end(&self, run_id: &str, report: &RunReport) -> Result<(), AgentError>;
+
+    /// The most recent finished runs, oldest first; stores that keep no history return none.
+    async fn load_reports(&self, _limit: usize) -> Result<Vec<RunReport>, AgentError> {
+        Ok(Vec::new())
+    }
+
+    /// Up to `k` stored notes relevant to `query`; stores without an index return none.
+    async fn recall(&self, _query: &str, _k: usize) -> Result<Vec<String>, AgentError> {
+        Ok(Vec::new())
+    }
+}
+
+/// Condenses prior runs into a note that seeds the next run's `Memory`.
+pub trait MemorySummarizer: Send + Sync {
+    fn summarize(&self, prior: &[RunReport]) -> String;
 }
 
 #[async_trait]
 pub trait SnapshotStore: Send + Sync {
     async fn save(&self, run_id: &str, step: Option<usize>, snapshot: &Snapshot) -> Result<(), AgentError>;
+
+    /// Store the snapshot captured after a failed step. Defaults to a regular step save.
+    async fn save_error(&self, run_id: &str, step: usize, snapshot: &Snapshot) -> Result<(), AgentError> {
+        self.save(run_id, Some(step), snapshot).await
+    }
+
+    /// Store the page as it was just before a step's action (`AgentConfig::capture_pre_action`).
+    /// Stores that keep one image per step ignore it.
+    async fn save_pre_action(&self, _run_id: &str, _step: usize, _snapshot: &Snapshot) -> Result<(), AgentError> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+pub trait ThreadStore: Send + Sync {
+    async fn save_thread(&self, run_id: &str, id: &ResponseId) -> Result<(), AgentError>;
+    async fn load_thread(&self, run_id: &str) -> Result<Option<ResponseId>, AgentError>;
+}
+
+/// Scrubs sensitive content from a snapshot before it is stored or shown to the reasoner.
+#[async_trait]
+pub trait Redactor: Send + Sync {
+    async fn redact(&self, snapshot: &mut Snapshot) -> Result<(), AgentError>;
 }
 
 #[async_trait]
@@ -214,11 +710,139 @@ pub trait PolicyEngine: Send + Sync {
 
 // ========================= Agent Core =========================
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AgentConfig {
     pub max_steps: usize,
+    #[serde(rename = "step_timeout_ms", with = "duration_ms")]
     pub step_timeout: Duration,
+    /// Budget for actions that load a page (navigation, `Submit`, `WaitForUrl`, `WaitForResponse`); `None` uses `step_timeout`.
+    #[serde(rename = "navigation_timeout_ms", with = "duration_ms_opt")]
+    pub navigation_timeout: Option<Duration>,
     pub scopes: Vec<Scope>,
+    pub screenshot_on_error: bool, // capture and store a snapshot when a step fails
+    pub max_unmapped: usize,       // abort on the Nth action the adapter cannot translate
+    pub highlight_actions: bool,   // outline each action target before acting (debugging aid)
+    pub screenshot_cadence: ScreenshotCadence,
+    pub max_reasoner_errors: usize, // transient reasoner failures retried per step before aborting
+    #[serde(rename = "reasoner_backoff_ms", with = "duration_ms")]
+    pub reasoner_backoff: Duration, // wait before each retry, multiplied by the attempt number
+    pub id_len: usize,               // length of generated run and snapshot ids
+    pub id_alphabet: Option<String>, // characters ids are drawn from; None uses nanoid's URL-safe set
+    pub summary_runs: usize,         // prior reports handed to the memory summarizer, if one is set
+    pub recall_k: usize,             // notes recalled from the memory store for the goal at run start
+    pub verify_success: bool,        // re-snapshot and ask `Reasoner::confirm_success` before finishing a run
+    /// Token budget per run. Before each step the agent stops with `RunStatus::Timeout` if
+    /// another turn as large as the last one would exceed it. Needs a reasoner that reports
+    /// `usage`.
+    pub max_tokens: Option<u64>,
+    /// Page opened before the goal's start URL, e.g. to accept a consent banner or prime caches.
+    /// It does not count against `max_steps` and its snapshot is not recorded.
+    pub warmup_url: Option<String>,
+    /// After every navigation (warmup, start URL, `NavGoto`), click away a cookie/consent
+    /// banner if the computer finds one. See `BrowserConfig::consent` for what is matched.
+    pub auto_dismiss_consent: bool,
+    /// When an action leaves the page looking the same, tell the reasoner so on its next turn
+    /// (`Memory::hint`), nudging it away from repeating the action.
+    pub nudge_on_no_change: bool,
+    /// When set, only these kinds of action are executed; anything else the reasoner proposes
+    /// is recorded as a denied step without consulting the policy or touching the computer.
+    pub allowed_actions: Option<HashSet<ActionKind>>,
+    /// While the page is blank (`about:blank`, no URL yet), deny every action but `NavGoto`, so
+    /// a run started without a start URL doesn't click around an empty page.
+    pub navigate_first: bool,
+    /// Capture a fresh snapshot right before each action and store it with
+    /// `SnapshotStore::save_pre_action` (`step_NNN_pre.png` on disk), next to the post-action one.
+    pub capture_pre_action: bool,
+    /// Wait a random time in `[min, max]` before each action, to pace a run like a person
+    /// would on sites that flag instant action sequences. In JSON: `[min_ms, max_ms]`.
+    #[serde(rename = "inter_action_delay_ms", with = "duration_ms_range")]
+    pub inter_action_delay: Option<(Duration, Duration)>,
+}
+
+/// When the agent asks the computer for a fresh screenshot after acting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScreenshotCadence {
+    /// Capture after every action.
+    EveryStep,
+    /// Capture only when the reasoner asks for one (`Thought::requires_screenshot`).
+    #[default]
+    WhenRequired,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            max_steps: 40,
+            step_timeout: Duration::from_millis(3000),
+            navigation_timeout: None,
+            scopes: vec![],
+            screenshot_on_error: true,
+            max_unmapped: 3,
+            highlight_actions: false,
+            screenshot_cadence: ScreenshotCadence::default(),
+            max_reasoner_errors: 0,
+            reasoner_backoff: Duration::from_millis(1000),
+            id_len: 21,
+            id_alphabet: None,
+            summary_runs: 5,
+            recall_k: 0,
+            verify_success: false,
+            max_tokens: None,
+            warmup_url: None,
+            auto_dismiss_consent: false,
+            nudge_on_no_change: false,
+            allowed_actions: None,
+            navigate_first: false,
+            capture_pre_action: false,
+            inter_action_delay: None,
+        }
+    }
+}
+
+impl AgentConfig {
+    /// A fresh id per `id_len`/`id_alphabet`. Ids name run directories, so a zero length or
+    /// an empty alphabet (or one over 255 characters, nanoid's limit) is an error rather than
+    /// an empty or surprising id.
+    pub fn new_id(&self) -> Result<String, AgentError> {
+        let len = self.id_len;
+        if len == 0 {
+            return Err(AgentError::Other("AgentConfig::id_len must be at least 1".into()));
+        }
+        match self.id_alphabet.as_deref() {
+            None => Ok(nanoid!(len)),
+            Some(alphabet) => {
+                let chars: Vec<char> = alphabet.chars().collect();
+                if chars.is_empty() || chars.len() > 255 {
+                    return Err(AgentError::Other(format!(
+                        "AgentConfig::id_alphabet must have 1 to 255 characters, got {}",
+                        chars.len()
+                    )));
+                }
+                Ok(nanoid!(len, &chars))
+            }
+        }
+    }
+
+    /// Timeout for a single action: page loads get `navigation_timeout` when set, key holds
+    /// `step_timeout` plus the hold, everything else `step_timeout`.
+    pub fn step_timeout_for(&self, action: &Action) -> Duration {
+        match action {
+            Action::NavGoto { .. }
+            | Action::NavBack
+            | Action::NavForward
+            | Action::Reload
+            | Action::Submit { .. }
+            | Action::WaitForUrl { .. }
+            | Action::WaitForResponse { .. } => {
+                self.navigation_timeout.unwrap_or(self.step_timeout)
+            }
+            // The hold itself shouldn't eat into the step budget
+            Action::KeyHold { hold_ms, .. } => self.step_timeout + Duration::from_millis(*hold_ms),
+            _ => self.step_timeout,
+        }
+    }
 }
 
 pub struct Agent<C, R, M, P>
@@ -235,6 +859,11 @@ where
     cfg: AgentConfig,
     snapshot_store: Option<Arc<dyn SnapshotStore>>, // optional sink for snapshots
     artifacts_dir: Option<PathBuf>,                  // optional dir for report.json alongside screenshots
+    redactor: Option<Arc<dyn Redactor>>,             // applied to every snapshot before it is saved or reasoned on
+    paused: Arc<AtomicBool>,                         // pause gate checked before each approved action
+    resume_signal: Arc<Notify>,                      // wakes a paused run
+    pending_hint: Arc<Mutex<Option<String>>>,        // operator hint for the next think
+    summarizer: Option<Arc<dyn MemorySummarizer>>,   // seeds Memory.notes from prior runs
 }
 
 impl<C, R, M, P> Agent<C, R, M, P>
@@ -253,9 +882,48 @@ where
             cfg,
             snapshot_store: None,
             artifacts_dir: None,
+            redactor: None,
+            paused: Arc::new(AtomicBool::new(false)),
+            resume_signal: Arc::new(Notify::new()),
+            pending_hint: Arc::new(Mutex::new(None)),
+            summarizer: None,
         }
     }
 
+    /// Start each run with a note summarizing the last `AgentConfig::summary_runs` reports
+    /// from the memory store.
+    pub fn with_summarizer(mut self, summarizer: Arc<dyn MemorySummarizer>) -> Self {
+        self.summarizer = Some(summarizer);
+        self
+    }
+
+    /// Shut down the computer (e.g. the Chromium process and its profile dir) once the agent
+    /// will run no more goals.
+    pub async fn close(&self) -> Result<(), AgentError> {
+        self.computer.close().await
+    }
+
+    /// Steer a running agent: `text` reaches the reasoner on its next `think` via
+    /// `Memory::hint`. A hint not yet consumed is replaced.
+    pub async fn hint(&self, text: impl Into<String>) {
+        *self.pending_hint.lock().await = Some(text.into());
+    }
+
+    /// Hold the run before its next action until `resume` is called. Unlike cancellation the
+    /// run stays alive and continues where it left off.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+        self.resume_signal.notify_waiters();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
     pub async fn run(&self, goal: &str, start_url: Option<&str>) -> Result<RunReport, AgentError> {
         let goal = Goal {
             task: goal.to_string(),
@@ -271,11 +939,41 @@ where
         goal: Goal,
         start_url: Option<&str>,
     ) -> Result<RunReport, AgentError> {
-        let run_id = nanoid!();
+        self.run_goal_with_id(&self.cfg.new_id()?, goal, start_url).await
+    }
+
+    /// `run_goal` under a caller-chosen run id (e.g. a job key). Re-running a crashed job with
+    /// the same id lets the reasoner resume what it saved for it, such as the CUA response
+    /// thread, instead of starting over. The id names the run's artifact directory, so it must
+    /// be a single non-empty path segment.
+    pub async fn run_goal_with_id(
+        &self,
+        run_id: &str,
+        goal: Goal,
+        start_url: Option<&str>,
+    ) -> Result<RunReport, AgentError> {
+        if run_id.is_empty() || run_id == "." || run_id == ".." || run_id.contains(['/', '\\']) {
+            return Err(AgentError::Other(format!("invalid run id: {:?}", run_id)));
+        }
+        let run_id = run_id.to_string();
         let start = Instant::now();
+        let deadline = goal.timeout_ms.map(|ms| start + Duration::from_millis(ms as u64));
+        // Deadline timer: flips `expired` when the run budget is spent, so in-flight awaits bail
+        // out. The guard aborts it however the run ends.
+        let (expire_tx, expired) = tokio::sync::watch::channel(false);
+        let _deadline_timer = AbortOnDrop(deadline.map(|d| {
+            tokio::spawn(async move {
+                tokio::time::sleep_until(d.into()).await;
+                let _ = expire_tx.send(true);
+            })
+        }));
         let mut metrics = RunMetrics::default();
         let mut steps: Vec<StepLog> = Vec::new();
         let mut last_error: Option<AgentError> = None;
+        let mut unmapped_count = 0usize;
+        let mut no_change = false; // the last action left the page as it was
+        // Rest of a `think_batch`; dropped as soon as one of them fails or is denied
+        let mut queued: std::collections::VecDeque<Thought> = std::collections::VecDeque::new();
 
         // Graceful shutdown: capture Ctrl-C and mark cancellation
         let cancelled = Arc::new(AtomicBool::new(false));
@@ -286,23 +984,113 @@ where
         });
 
         self.memory.write_run_start(&run_id, &goal).await?;
+        match self.reasoner.resume(&run_id).await {
+            Ok(true) => info!(run_id = %run_id, "reasoner resumed saved state"),
+            Ok(false) => {}
+            Err(e) => warn!(run_id = %run_id, "reasoner resume failed, starting fresh: {}", e),
+        }
+
+        // Like `before_deadline!` in the step loop, for the setup before it; a run that times
+        // out before its start page loads reports an empty snapshot
+        macro_rules! before_setup_deadline {
+            ($fut:expr, $snapshot:expr) => {{
+                let out = within_deadline(expired.clone(), $fut).await;
+                match out {
+                    Some(v) => v,
+                    None => {
+                        warn!("run deadline passed during setup");
+                        metrics.success = false;
+                        metrics.time_ms = start.elapsed().as_millis();
+                        return self
+                            .finish(
+                                run_id,
+                                goal,
+                                steps,
+                                metrics,
+                                $snapshot,
+                                RunStatus::Timeout,
+                                "Run budget exceeded",
+                                None,
+                            )
+                            .await;
+                    }
+                }
+            }};
+        }
+
+        if let Some(url) = &self.cfg.warmup_url {
+            // Not a step: its snapshot is neither stored nor shown to the reasoner
+            info!(url = %url, "warmup navigation");
+            before_setup_deadline!(self.computer.open_url(url), Snapshot::new(None, None))?;
+            before_setup_deadline!(self.dismiss_consent(), Snapshot::new(None, None));
+        }
 
         let mut last_snapshot = match start_url {
-            Some(url) => self.computer.open_url(url).await?,
-            None => self.computer.snapshot().await?,
+            Some(url) => {
+                let snap = before_setup_deadline!(self.computer.open_url(url), Snapshot::new(None, None))?;
+                if before_setup_deadline!(self.dismiss_consent(), snap) {
+                    before_setup_deadline!(self.computer.snapshot(), Snapshot::new(None, None))?
+                } else {
+                    snap
+                }
+            }
+            None => before_setup_deadline!(self.computer.snapshot(), Snapshot::new(None, None))?,
         };
+        self.prepare_snapshot(&mut last_snapshot).await?;
         if let Some(store) = &self.snapshot_store {
             let _ = store.save(&run_id, None, &last_snapshot).await;
         }
 
-        let memory = Memory {
+        let mut memory = Memory {
             run_id: run_id.clone(),
             notes: Vec::new(),
+            hint: None,
         };
+        if let Some(summarizer) = &self.summarizer {
+            let prior = before_setup_deadline!(self.memory.load_reports(self.cfg.summary_runs), last_snapshot)?;
+            if !prior.is_empty() {
+                memory.notes.push(summarizer.summarize(&prior));
+            }
+        }
+        if self.cfg.recall_k > 0 {
+            let recalled = before_setup_deadline!(self.memory.recall(&goal.task, self.cfg.recall_k), last_snapshot)?;
+            memory.notes.extend(recalled);
+        }
 
-        let deadline = goal.timeout_ms.map(|ms| start + Duration::from_millis(ms as u64));
+        // The reasoner's usage is cumulative across runs, so budget against the delta from here
+        let tokens_base = self.reasoner.usage().await.map_or(0, |u| u.total_tokens);
+        let mut tokens_used = 0u64;
 
         for i in 0..self.cfg.max_steps {
+            // Await a step future, or finish as timed out the moment the goal's deadline passes,
+            // so a hung reasoner or page cannot hold the run past `Goal::timeout_ms`
+            macro_rules! before_deadline {
+                ($fut:expr) => {{
+                    let out = within_deadline(expired.clone(), $fut).await;
+                    match out {
+                        Some(v) => v,
+                        None => {
+                            warn!(step = i, "run deadline passed mid-step");
+                            metrics.success = false;
+                            metrics.steps = i;
+                            metrics.time_ms = start.elapsed().as_millis();
+                            return self
+                                .finish(
+                                    run_id,
+                                    goal,
+                                    steps,
+                                    metrics,
+                                    last_snapshot,
+                                    RunStatus::Timeout,
+                                    "Run budget exceeded",
+                                    None,
+                                )
+                                .await;
+                        }
+                    }
+                }};
+            }
+
             if cancelled.load(Ordering::SeqCst) {
                 metrics.success = false;
                 metrics.steps = i;
@@ -336,11 +1124,48 @@ where
                         .await;
                 }
             }
+            if let Some(max) = self.cfg.max_tokens {
+                let used = self.reasoner.usage().await.map_or(0, |u| u.total_tokens.saturating_sub(tokens_base));
+                let last_turn = used.saturating_sub(tokens_used);
+                tokens_used = used;
+                if used.saturating_add(last_turn) > max {
+                    info!(step = i, used, max, "token budget exhausted");
+                    metrics.success = false;
+                    metrics.steps = i;
+                    metrics.time_ms = start.elapsed().as_millis();
+                    return self
+                        .finish(
+                            run_id,
+                            goal,
+                            steps,
+                            metrics,
+                            last_snapshot,
+                            RunStatus::Timeout,
+                            "Budget exhausted",
+                            Some(format!("used {} of {} tokens", used, max)),
+                        )
+                        .await;
+                }
+            }
 
-            let success = self
-                .reasoner
-                .success(&goal, &last_snapshot, &memory)
-                .await?;
+            let mut reasoner_errors = 0;
+            // Queued thoughts from a batch run before the reasoner is asked anything again
+            let mut success = queued.is_empty()
+                && before_deadline!(self.with_reasoner_retries(&run_id, &mut reasoner_errors, i, start, &mut steps, || {
+                    self.reasoner.success(&goal, &last_snapshot, &memory)
+                }))?;
+            if success && self.cfg.verify_success {
+                // Observe again and have the reasoner confirm before trusting a possibly premature success
+                let mut snap = before_deadline!(self.computer.snapshot())?;
+                self.prepare_snapshot(&mut snap).await?;
+                last_snapshot = snap;
+                success = before_deadline!(self.with_reasoner_retries(&run_id, &mut reasoner_errors, i, start, &mut steps, || {
+                    self.reasoner.confirm_success(&goal, &last_snapshot, &memory)
+                }))?;
+                if !success {
+                    info!(step = i, "success not confirmed on re-check, continuing");
+                }
+            }
             if success {
                 metrics.success = true;
                 metrics.steps = i;
@@ -359,16 +1184,44 @@ where
                     .await;
             }
 
-            let thought = match self
-                .reasoner
-                .think(&goal, &memory, &last_snapshot, last_error.as_ref())
-                .await
-            {
+            if queued.is_empty() {
+                if let Some(hint) = self.pending_hint.lock().await.take() {
+                    info!(step = i, "operator hint: {}", hint);
+                    memory.hint = Some(hint);
+                }
+                if std::mem::take(&mut no_change) && self.cfg.nudge_on_no_change {
+                    memory.hint = Some(match memory.hint.take() {
+                        Some(h) => format!("{}\n{}", NO_CHANGE_NOTE, h),
+                        None => NO_CHANGE_NOTE.to_string(),
+                    });
+                }
+            }
+            let thought = match queued.pop_front() {
+                Some(t) => Ok(t),
+                None => {
+                    let planned = before_deadline!(self.with_reasoner_retries(&run_id, &mut reasoner_errors, i, start, &mut steps, || {
+                        self.reasoner.think_batch(&goal, &memory, &last_snapshot, last_error.as_ref())
+                    }));
+                    planned.map(|batch| {
+                        if batch.len() > 1 {
+                            info!(step = i, thoughts = batch.len(), "reasoner planned a batch");
+                        }
+                        queued = batch.into();
+                        queued.pop_front().unwrap_or_default()
+                    })
+                }
+            };
+            memory.hint = None;
+            let thought = match thought {
                 Ok(t) => t,
                 Err(err) => {
                     metrics.success = false;
                     metrics.steps = i;
                     metrics.time_ms = start.elapsed().as_millis();
+                    let msg = match err {
+                        AgentError::Refused(_) => "Model refused",
+                        _ => "Reasoner error",
+                    };
                     return self
                         .finish(
                             run_id,
@@ -377,12 +1230,33 @@ where
                             metrics,
                             last_snapshot,
                             RunStatus::Error,
-                            "Reasoner error",
+                            msg,
                             Some(format!("{}", err)),
                         )
                         .await;
                 }
             };
+            if let Some(raw) = &thought.unmapped_action {
+                unmapped_count += 1;
+                warn!(step = i, count = unmapped_count, "unsupported model action: {}", raw);
+                if unmapped_count >= self.cfg.max_unmapped {
+                    metrics.success = false;
+                    metrics.steps = i;
+                    metrics.time_ms = start.elapsed().as_millis();
+                    return self
+                        .finish(
+                            run_id,
+                            goal,
+                            steps,
+                            metrics,
+                            last_snapshot,
+                            RunStatus::Error,
+                            "Too many unmapped actions",
+                            Some(format!("unsupported model action: {}", raw)),
+                        )
+                        .await;
+                }
+            }
             let maybe_action = thought.action.clone();
             let mut step_log = StepLog {
                 step: i,
@@ -393,6 +1267,7 @@ where
                 snapshot_id: None,
                 error: None,
                 timestamp_ms: Instant::now().duration_since(start).as_millis(),
+                hit_target: None,
             };
             info!(step = i, plan = %thought.plan, has_action = %maybe_action.is_some(), "agent step");
 
@@ -414,10 +1289,34 @@ where
             }
 
             if let Some(action) = &maybe_action {
-                let approval = self.policy.approve(&self.cfg.scopes, action).await?;
-                step_log.approval = Some(approval.clone());
-                if !approval.granted {
-                    last_error = Some(AgentError::Denied(
+                let kind = action.kind();
+                if self.cfg.allowed_actions.as_ref().is_some_and(|allowed| !allowed.contains(&kind)) {
+                    queued.clear();
+                    let reason = format!("{:?} actions are not allowed in this run", kind);
+                    step_log.approval = Some(Approval { granted: false, scope: None, reason: Some(reason.clone()) });
+                    last_error = Some(AgentError::Other(reason));
+                    step_log.result_hint = "denied".into();
+                    self.memory.write_step(&run_id, &step_log).await?;
+                    steps.push(step_log);
+                    info!(step = i, ?kind, "action kind not allowed");
+                    continue;
+                }
+                if self.cfg.navigate_first && kind != ActionKind::NavGoto && is_blank_page(last_snapshot.url.as_deref()) {
+                    queued.clear();
+                    let reason = "the page is blank; navigate to a URL first".to_string();
+                    step_log.approval = Some(Approval { granted: false, scope: None, reason: Some(reason.clone()) });
+                    last_error = Some(AgentError::Other(reason));
+                    step_log.result_hint = "denied".into();
+                    self.memory.write_step(&run_id, &step_log).await?;
+                    steps.push(step_log);
+                    info!(step = i, ?kind, "action on blank page denied");
+                    continue;
+                }
+                let approval = self.policy.approve(&self.cfg.scopes, action).await?;
+                step_log.approval = Some(approval.clone());
+                if !approval.granted {
+                    queued.clear();
+                    last_error = Some(AgentError::Denied(
                         approval.scope.unwrap_or(Scope::BrowserNavigate),
                     ));
                     step_log.result_hint = "denied".into();
@@ -426,31 +1325,92 @@ where
                     info!(step = i, "action denied by policy");
                     continue;
                 }
-                info!(step = i, action = ?action, "action approved");
+                info!(step = i, ?kind, action = ?action, "action approved");
+                self.wait_if_paused(i).await;
             }
 
-            let result = if let Some(action) = maybe_action {
-                self.computer.act(&action, self.cfg.step_timeout).await
-            } else {
-                Ok(ActionResult {
-                    snapshot: self.computer.snapshot().await?,
-                    changed: false,
-                    message: Some("think".to_string()),
-                })
+            if let Some(action) = &maybe_action {
+                metrics.record(action.kind());
+            }
+            let result = match maybe_action.map(|action| self.in_artifacts_dir(action, &run_id)) {
+                Some(Err(err)) => Err(err),
+                Some(Ok(action)) => {
+                    if let Some((min, max)) = self.cfg.inter_action_delay {
+                        before_deadline!(tokio::time::sleep(jitter(min, max)));
+                    }
+                    if let (true, Some(store)) = (self.cfg.capture_pre_action, &self.snapshot_store) {
+                        // Before any highlight, so the image shows what the model decided on
+                        let pre = match before_deadline!(self.computer.snapshot()) {
+                            Ok(mut snap) => self.prepare_snapshot(&mut snap).await.map(|_| snap),
+                            Err(e) => Err(e),
+                        };
+                        match pre {
+                            Ok(snap) => {
+                                let _ = store.save_pre_action(&run_id, i, &snap).await;
+                            }
+                            Err(e) => warn!("step {} pre-action snapshot failed: {}", i, e),
+                        }
+                    }
+                    if self.cfg.highlight_actions {
+                        if let Some(target) = action.target() {
+                            if let Err(e) = self.computer.highlight(target).await {
+                                warn!("step {} highlight failed: {}", i, e);
+                            }
+                        }
+                    }
+                    // Never let one action run past the goal's overall deadline
+                    let timeout = match deadline {
+                        Some(d) => self.cfg.step_timeout_for(&action).min(d.saturating_duration_since(Instant::now())),
+                        None => self.cfg.step_timeout_for(&action),
+                    };
+                    if self.cfg.screenshot_cadence == ScreenshotCadence::WhenRequired && !thought.requires_screenshot {
+                        before_deadline!(self.computer.act_without_screenshot(&action, timeout))
+                    } else {
+                        before_deadline!(self.computer.act(&action, timeout))
+                    }
+                }
+                None => {
+                    let snapshot = if self.cfg.screenshot_cadence == ScreenshotCadence::WhenRequired && !thought.requires_screenshot {
+                        before_deadline!(self.computer.snapshot_light())?
+                    } else {
+                        before_deadline!(self.computer.observe())?
+                    };
+                    Ok(ActionResult {
+                        snapshot,
+                        changed: false,
+                        message: Some("think".to_string()),
+                        hit_target: None,
+                    })
+                }
             };
 
             match result {
-                Ok(out) => {
+                Ok(mut out) => {
+                    if matches!(thought.action, Some(Action::NavGoto { .. })) && self.dismiss_consent().await {
+                        out.snapshot = self.computer.snapshot().await?;
+                    }
+                    self.prepare_snapshot(&mut out.snapshot).await?;
+                    // Without a fresh capture the image is the previous one, so only the adapter's flag counts
+                    let captured = self.cfg.screenshot_cadence == ScreenshotCadence::EveryStep || thought.requires_screenshot;
+                    if thought.action.is_some() && captured && last_snapshot.image_base64.is_some() {
+                        // Screenshots on both sides: compare them rather than trust the adapter
+                        out.changed = !out.snapshot.content_eq(&last_snapshot);
+                    }
+                    // A page still loading may yet change, so it never counts as unchanged
+                    no_change = thought.action.is_some() && !out.snapshot.is_loading() && !out.changed;
                     last_snapshot = out.snapshot.clone();
                     if let Some(store) = &self.snapshot_store {
                         let _ = store.save(&memory.run_id, Some(i), &last_snapshot).await;
                     }
-                    step_log.result_hint = if out.changed {
+                    step_log.result_hint = if thought.unmapped_action.is_some() {
+                        "unmapped_action".into()
+                    } else if out.changed {
                         "changed".into()
                     } else {
                         "unchanged".into()
                     };
                     step_log.snapshot_id = Some(last_snapshot.id.clone());
+                    step_log.hit_target = out.hit_target;
                     last_error = None;
                     self.memory.write_step(&run_id, &step_log).await?;
                     steps.push(step_log);
@@ -460,8 +1420,27 @@ where
                     warn!("step {} failed: {}", i, err);
                     step_log.error = Some(format!("{}", err));
                     step_log.result_hint = "error".into();
+                    if self.cfg.screenshot_on_error {
+                        // Best-effort: the failure state is what we most want to see
+                        let snap = match before_deadline!(self.computer.snapshot()) {
+                            Ok(mut snap) => self.prepare_snapshot(&mut snap).await.map(|_| snap),
+                            Err(e) => Err(e),
+                        };
+                        match snap {
+                            Ok(snap) => {
+                                if let Some(store) = &self.snapshot_store {
+                                    let _ = store.save_error(&run_id, i, &snap).await;
+                                }
+                                step_log.snapshot_id = Some(snap.id.clone());
+                                last_snapshot = snap;
+                            }
+                            Err(e) => warn!("step {} error snapshot failed: {}", i, e),
+                        }
+                    }
                     self.memory.write_step(&run_id, &step_log).await?;
                     steps.push(step_log);
+                    // The rest of a batch assumed this action worked; let the reasoner replan
+                    queued.clear();
                     last_error = Some(err);
                 }
             }
@@ -484,6 +1463,131 @@ where
             .await
     }
 
+    /// Point a `SavePdf` path into `<artifacts_dir>/<run_id>/`, next to report.json. The path
+    /// comes from the model, so anything that could land outside that directory (absolute, or
+    /// with a `..` component) fails the action, as does a run without an artifacts dir.
+    fn in_artifacts_dir(&self, action: Action, run_id: &str) -> Result<Action, AgentError> {
+        let Action::SavePdf { path } = action else { return Ok(action) };
+        let Some(dir) = &self.artifacts_dir else {
+            return Err(AgentError::Computer("save_pdf needs an artifacts dir (Agent::with_artifacts_dir)".into()));
+        };
+        let contained = !path.is_empty()
+            && Path::new(&path)
+                .components()
+                .all(|c| matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir));
+        if !contained {
+            return Err(AgentError::Computer(format!(
+                "save_pdf path {:?} must be relative to the artifacts dir, without '..'",
+                path
+            )));
+        }
+        Ok(Action::SavePdf { path: dir.join(run_id).join(path).to_string_lossy().into_owned() })
+    }
+
+    /// Per `auto_dismiss_consent`; a failed attempt is logged, not fatal.
+    async fn dismiss_consent(&self) -> bool {
+        if !self.cfg.auto_dismiss_consent {
+            return false;
+        }
+        match self.computer.dismiss_consent().await {
+            Ok(dismissed) => {
+                if dismissed {
+                    info!("consent banner dismissed");
+                }
+                dismissed
+            }
+            Err(e) => {
+                warn!("consent banner dismissal failed: {}", e);
+                false
+            }
+        }
+    }
+
+    async fn wait_if_paused(&self, step: usize) {
+        loop {
+            // Register before checking the flag so a resume in between is not missed
+            let resumed = self.resume_signal.notified();
+            if !self.is_paused() {
+                return;
+            }
+            info!(step, "run paused");
+            resumed.await;
+        }
+    }
+
+    /// Re-id a snapshot from the computer with the configured id format (unless the computer
+    /// keeps its ids, see `Computer::keeps_snapshot_ids`), then redact it.
+    async fn prepare_snapshot(&self, snapshot: &mut Snapshot) -> Result<(), AgentError> {
+        if !self.computer.keeps_snapshot_ids() || snapshot.id.is_empty() {
+            snapshot.id = self.cfg.new_id()?;
+        }
+        match &self.redactor {
+            Some(r) => r.redact(snapshot).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Run a reasoner call, retrying transient failures per `retry_reasoner`.
+    #[allow(clippy::too_many_arguments)]
+    async fn with_reasoner_retries<T, F, Fut>(
+        &self,
+        run_id: &str,
+        errors: &mut usize,
+        step: usize,
+        start: Instant,
+        steps: &mut Vec<StepLog>,
+        mut call: F,
+    ) -> Result<T, AgentError>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, AgentError>>,
+    {
+        loop {
+            match call().await {
+                Ok(v) => return Ok(v),
+                Err(err) => {
+                    if !self.retry_reasoner(run_id, &err, errors, step, start, steps).await? {
+                        return Err(err);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Log a transient reasoner failure and back off; false once the per-step budget is spent
+    /// or the error is not worth retrying.
+    async fn retry_reasoner(
+        &self,
+        run_id: &str,
+        err: &AgentError,
+        errors: &mut usize,
+        step: usize,
+        start: Instant,
+        steps: &mut Vec<StepLog>,
+    ) -> Result<bool, AgentError> {
+        if !matches!(err, AgentError::Reasoner(_)) || *errors >= self.cfg.max_reasoner_errors {
+            return Ok(false);
+        }
+        *errors += 1;
+        warn!(step, attempt = *errors, "reasoner error, retrying: {}", err);
+        let step_log = StepLog {
+            step,
+            plan: String::new(),
+            action: None,
+            approval: None,
+            result_hint: "reasoner_error".into(),
+            snapshot_id: None,
+            error: Some(err.to_string()),
+            timestamp_ms: Instant::now().duration_since(start).as_millis(),
+            hit_target: None,
+        };
+        self.memory.write_step(run_id, &step_log).await?;
+        steps.push(step_log);
+        tokio::time::sleep(self.cfg.reasoner_backoff * *errors as u32).await;
+        Ok(true)
+    }
+
+    #[allow(clippy::too_many_arguments)]
     async fn finish(
         &self,
         run_id: String,
@@ -511,7 +1615,7 @@ where
                 warn!("artifacts create_dir failed: {}", e);
             } else {
                 let report_path = run_dir.join("report.json");
-                match serde_json::to_vec_pretty(&report) {
+                match report.to_json_pretty() {
                     Ok(buf) => {
                         if let Err(e) = async_fs::write(&report_path, buf).await {
                             warn!("artifacts write report failed: {}", e);
@@ -530,6 +1634,110 @@ where
 
 // ========================= Defaults & Helpers =========================
 
+const NO_CHANGE_NOTE: &str = "The last action produced no visible change on the page. \
+Do not repeat it; try a different element, position or approach.";
+
+/// No page loaded yet: no URL, `about:blank` or a new-tab page.
+fn is_blank_page(url: Option<&str>) -> bool {
+    match url.map(str::trim) {
+        None | Some("") => true,
+        Some(u) => u.starts_with("about:") || u.starts_with("chrome://newtab") || u.starts_with("chrome-search://"),
+    }
+}
+
+/// `fut`'s output, or None if the run's deadline timer fires first.
+async fn within_deadline<T>(
+    mut expired: tokio::sync::watch::Receiver<bool>,
+    fut: impl std::future::Future<Output = T>,
+) -> Option<T> {
+    tokio::select! {
+        out = fut => Some(out),
+        // A closed channel means no deadline was set: never expire
+        Ok(_) = expired.wait_for(|e| *e) => None,
+    }
+}
+
+/// Aborts the wrapped task when dropped.
+struct AbortOnDrop(Option<tokio::task::JoinHandle<()>>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        if let Some(task) = &self.0 {
+            task.abort();
+        }
+    }
+}
+
+/// Serde adapter storing a `Duration` as integer milliseconds.
+pub mod duration_ms {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u64(d.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        Ok(Duration::from_millis(u64::deserialize(d)?))
+    }
+}
+
+/// Serde helper for optional `Duration` fields stored as integer milliseconds.
+pub mod duration_ms_opt {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(d: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+        match d {
+            Some(d) => s.serialize_some(&(d.as_millis() as u64)),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<u64>::deserialize(d)?.map(Duration::from_millis))
+    }
+}
+
+/// Serde helper for an optional `(min, max)` `Duration` pair stored as `[min_ms, max_ms]`.
+pub mod duration_ms_range {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(d: &Option<(Duration, Duration)>, s: S) -> Result<S::Ok, S::Error> {
+        match d {
+            Some((min, max)) => s.serialize_some(&(min.as_millis() as u64, max.as_millis() as u64)),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<(Duration, Duration)>, D::Error> {
+        Ok(Option::<(u64, u64)>::deserialize(d)?.map(|(min, max)| (Duration::from_millis(min), Duration::from_millis(max))))
+    }
+}
+
+/// A random duration in `[min, max]` (bounds in either order). Randomness comes from std's
+/// per-instance hasher keys, which is plenty for pacing and avoids a `rand` dependency.
+fn jitter(min: Duration, max: Duration) -> Duration {
+    use std::hash::{BuildHasher, Hasher};
+    let (lo, hi) = if min <= max { (min, max) } else { (max, min) };
+    let span = (hi - lo).as_millis() as u64;
+    if span == 0 {
+        return lo;
+    }
+    let mut h = std::collections::hash_map::RandomState::new().build_hasher();
+    h.write_u128(now_ms());
+    lo + Duration::from_millis(h.finish() % (span + 1))
+}
+
+/// Milliseconds since the Unix epoch; 0 if the system clock is before 1970.
+fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default()
+}
+
 pub struct NullMemoryStore;
 
 #[async_trait]
@@ -547,6 +1755,77 @@ impl MemoryStore for NullMemoryStore {
     }
 }
 
+/// Keeps finished run reports in memory so later runs can be summarized from them.
+#[derive(Default)]
+pub struct InMemoryStore {
+    reports: Mutex<Vec<RunReport>>,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl MemoryStore for InMemoryStore {
+    async fn write_run_start(&self, _run_id: &str, _goal: &Goal) -> Result<(), AgentError> {
+        Ok(())
+    }
+
+    async fn write_step(&self, _run_id: &str, _step: &StepLog) -> Result<(), AgentError> {
+        Ok(())
+    }
+
+    async fn write_run_end(&self, _run_id: &str, report: &RunReport) -> Result<(), AgentError> {
+        self.reports.lock().await.push(report.clone());
+        Ok(())
+    }
+
+    async fn load_reports(&self, limit: usize) -> Result<Vec<RunReport>, AgentError> {
+        let reports = self.reports.lock().await;
+        Ok(reports[reports.len().saturating_sub(limit)..].to_vec())
+    }
+}
+
+/// One line per prior run (goal, outcome, final message), truncated to the most recent
+/// `max_chars`.
+pub struct ConcatSummarizer {
+    pub max_chars: usize,
+}
+
+impl Default for ConcatSummarizer {
+    fn default() -> Self {
+        Self { max_chars: 2000 }
+    }
+}
+
+impl MemorySummarizer for ConcatSummarizer {
+    fn summarize(&self, prior: &[RunReport]) -> String {
+        let mut lines = vec!["Earlier runs:".to_string()];
+        for r in prior {
+            let mut line = format!("- {} -> {:?} after {} steps", r.goal.task, r.status, r.metrics.steps);
+            if let Some(err) = &r.error {
+                line.push_str(&format!(" ({})", err));
+            }
+            if let Some(last) = r.steps.iter().rev().find(|s| !s.plan.trim().is_empty()) {
+                line.push_str(&format!("; last note: {}", last.plan.trim()));
+            }
+            lines.push(line.replace('\n', " "));
+        }
+        let text = lines.join("\n");
+        if text.len() <= self.max_chars {
+            return text;
+        }
+        // Keep the tail: the latest runs matter most
+        let mut start = text.len() - self.max_chars;
+        while !text.is_char_boundary(start) {
+            start += 1;
+        }
+        text[start..].to_string()
+    }
+}
+
 pub struct DiskSnapshotStore {
     base_dir: PathBuf,
 }
@@ -557,9 +1836,8 @@ impl DiskSnapshotStore {
     }
 }
 
-#[async_trait]
-impl SnapshotStore for DiskSnapshotStore {
-    async fn save(&self, run_id: &str, step: Option<usize>, snapshot: &Snapshot) -> Result<(), AgentError> {
+impl DiskSnapshotStore {
+    async fn write_png(&self, run_id: &str, name: String, snapshot: &Snapshot) -> Result<(), AgentError> {
         let dir = self.base_dir.join(run_id);
         async_fs::create_dir_all(&dir)
             .await
@@ -568,10 +1846,6 @@ impl SnapshotStore for DiskSnapshotStore {
             let png = B64
                 .decode(b64)
                 .map_err(|e| AgentError::Memory(format!("b64 decode: {}", e)))?;
-            let name = match step {
-                Some(s) => format!("step_{:03}.png", s),
-                None => "start.png".to_string(),
-            };
             let path = dir.join(name);
             async_fs::write(&path, &png)
                 .await
@@ -581,6 +1855,58 @@ impl SnapshotStore for DiskSnapshotStore {
     }
 }
 
+#[async_trait]
+impl SnapshotStore for DiskSnapshotStore {
+    async fn save(&self, run_id: &str, step: Option<usize>, snapshot: &Snapshot) -> Result<(), AgentError> {
+        let name = match step {
+            Some(s) => format!("step_{:03}.png", s),
+            None => "start.png".to_string(),
+        };
+        self.write_png(run_id, name, snapshot).await
+    }
+
+    async fn save_error(&self, run_id: &str, step: usize, snapshot: &Snapshot) -> Result<(), AgentError> {
+        self.write_png(run_id, format!("step_{:03}_error.png", step), snapshot).await
+    }
+
+    async fn save_pre_action(&self, run_id: &str, step: usize, snapshot: &Snapshot) -> Result<(), AgentError> {
+        self.write_png(run_id, format!("step_{:03}_pre.png", step), snapshot).await
+    }
+}
+
+/// Stores the CUA response thread id as `<base>/<run_id>/thread_id`.
+pub struct DiskThreadStore {
+    base_dir: PathBuf,
+}
+
+impl DiskThreadStore {
+    pub fn new<P: AsRef<Path>>(base: P) -> Self {
+        Self { base_dir: base.as_ref().to_path_buf() }
+    }
+}
+
+#[async_trait]
+impl ThreadStore for DiskThreadStore {
+    async fn save_thread(&self, run_id: &str, id: &ResponseId) -> Result<(), AgentError> {
+        let dir = self.base_dir.join(run_id);
+        async_fs::create_dir_all(&dir)
+            .await
+            .map_err(|e| AgentError::Memory(format!("create_dir: {}", e)))?;
+        async_fs::write(dir.join("thread_id"), &id.0)
+            .await
+            .map_err(|e| AgentError::Memory(format!("write: {}", e)))
+    }
+
+    async fn load_thread(&self, run_id: &str) -> Result<Option<ResponseId>, AgentError> {
+        match async_fs::read_to_string(self.base_dir.join(run_id).join("thread_id")).await {
+            Ok(s) if !s.trim().is_empty() => Ok(Some(ResponseId(s.trim().to_string()))),
+            Ok(_) => Ok(None),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(AgentError::Memory(format!("read: {}", e))),
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct AllowAllPolicy;
 
@@ -597,25 +1923,15 @@ pub struct NoopComputer;
 #[async_trait]
 impl Computer for NoopComputer {
     async fn open_url(&self, url: &str) -> Result<Snapshot, AgentError> {
-        Ok(Snapshot {
-            id: nanoid!(),
-            url: Some(url.to_string()),
-            title: Some("noop".to_string()),
-            image_base64: None,
-            dom_summary: Some("<noop/>".to_string()),
-            captured_at_ms: 0,
-        })
+        Ok(Snapshot::new(Some(url.to_string()), None)
+            .with_title("noop")
+            .with_dom_summary("<noop/>"))
     }
 
     async fn snapshot(&self) -> Result<Snapshot, AgentError> {
-        Ok(Snapshot {
-            id: nanoid!(),
-            url: Some("about:blank".to_string()),
-            title: Some("noop".to_string()),
-            image_base64: None,
-            dom_summary: Some("<noop/>".to_string()),
-            captured_at_ms: 0,
-        })
+        Ok(Snapshot::new(Some("about:blank".to_string()), None)
+            .with_title("noop")
+            .with_dom_summary("<noop/>"))
     }
 
     async fn find(&self, locator: &Locator, _timeout: Duration) -> Result<DomNode, AgentError> {
@@ -624,7 +1940,87 @@ impl Computer for NoopComputer {
 
     async fn act(&self, _action: &Action, _timeout: Duration) -> Result<ActionResult, AgentError> {
         let snap = self.snapshot().await?;
-        Ok(ActionResult { snapshot: snap, changed: true, message: Some("noop".to_string()) })
+        Ok(ActionResult { snapshot: snap, changed: true, message: Some("noop".to_string()), hit_target: None })
+    }
+}
+
+/// Records every `(Action, ActionResult)` produced by the wrapped computer, e.g. for golden files.
+pub struct RecordingComputer<C: Computer> {
+    inner: C,
+    log: Arc<Mutex<Vec<(Action, ActionResult)>>>,
+}
+
+impl<C: Computer> RecordingComputer<C> {
+    pub fn new(inner: C) -> Self {
+        Self { inner, log: Arc::new(Mutex::new(Vec::new())) }
+    }
+
+    /// Shared handle to the recording; stays valid after the computer is moved into an agent.
+    pub fn log(&self) -> Arc<Mutex<Vec<(Action, ActionResult)>>> {
+        self.log.clone()
+    }
+}
+
+forward_computer!([C: Computer] RecordingComputer<C>, inner, {
+    async fn act(&self, action: &Action, timeout: Duration) -> Result<ActionResult, AgentError> {
+        let out = self.inner.act(action, timeout).await?;
+        self.log.lock().await.push((action.clone(), out.clone()));
+        Ok(out)
+    }
+
+    async fn act_without_screenshot(&self, action: &Action, timeout: Duration) -> Result<ActionResult, AgentError> {
+        let out = self.inner.act_without_screenshot(action, timeout).await?;
+        self.log.lock().await.push((action.clone(), out.clone()));
+        Ok(out)
+    }
+});
+
+/// Serves results captured by `RecordingComputer` in order, without touching a browser.
+pub struct ReplayComputer {
+    results: Mutex<std::collections::VecDeque<(Action, ActionResult)>>,
+    last: Mutex<Option<Snapshot>>,
+}
+
+impl ReplayComputer {
+    pub fn new(recorded: Vec<(Action, ActionResult)>) -> Self {
+        Self { results: Mutex::new(recorded.into()), last: Mutex::new(None) }
+    }
+}
+
+#[async_trait]
+impl Computer for ReplayComputer {
+    async fn open_url(&self, url: &str) -> Result<Snapshot, AgentError> {
+        let snap = Snapshot::new(Some(url.to_string()), None);
+        *self.last.lock().await = Some(snap.clone());
+        Ok(snap)
+    }
+
+    async fn snapshot(&self) -> Result<Snapshot, AgentError> {
+        let last = self.last.lock().await.clone();
+        Ok(last.unwrap_or_else(|| Snapshot::new(Some("about:blank".to_string()), None)))
+    }
+
+    async fn find(&self, locator: &Locator, _timeout: Duration) -> Result<DomNode, AgentError> {
+        Ok(DomNode { locator: locator.clone(), description: Some("replay".to_string()), rect: None })
+    }
+
+    async fn act(&self, action: &Action, _timeout: Duration) -> Result<ActionResult, AgentError> {
+        let (recorded, out) = self
+            .results
+            .lock()
+            .await
+            .pop_front()
+            .ok_or_else(|| AgentError::Computer("replay exhausted".into()))?;
+        if serde_json::to_value(&recorded).ok() != serde_json::to_value(action).ok() {
+            warn!(expected = ?recorded, got = ?action, "replay diverged from recording");
+        }
+        *self.last.lock().await = Some(out.snapshot.clone());
+        Ok(out)
+    }
+
+    /// Replayed snapshots keep their recorded ids, so step logs line up with the recording.
+    fn keeps_snapshot_ids(&self) -> bool {
+        true
     }
 }
 
@@ -640,7 +2036,7 @@ impl Reasoner for SimpleReasoner {
         _snapshot: &Snapshot,
         _last_error: Option<&AgentError>,
     ) -> Result<Thought, AgentError> {
-        Ok(Thought { plan: format!("Plan: {}", goal.task), action: None, rationale: Some("noop".to_string()) })
+        Ok(Thought { plan: format!("Plan: {}", goal.task), rationale: Some("noop".to_string()), ..Default::default() })
     }
 
     async fn success(
@@ -667,12 +2063,54 @@ impl<C: Computer, R: Reasoner> Agent<C, R, NullMemoryStore, AllowAllPolicy> {
         self.artifacts_dir = Some(dir.into());
         self
     }
+
+    pub fn with_redactor(mut self, redactor: Arc<dyn Redactor>) -> Self {
+        self.redactor = Some(redactor);
+        self
+    }
+}
+
+/// Blacks out every element matching the configured CSS selectors (password fields,
+/// account numbers, ...). Snapshots without an image are left alone.
+pub struct SelectorRedactor {
+    browser: Browser,
+    selectors: Vec<String>,
+}
+
+impl SelectorRedactor {
+    pub fn new(page: chromiumoxide::Page, selectors: Vec<String>) -> Self {
+        Self { browser: Browser::from_page(page), selectors }
+    }
+}
+
+#[async_trait]
+impl Redactor for SelectorRedactor {
+    async fn redact(&self, snapshot: &mut Snapshot) -> Result<(), AgentError> {
+        let Some(b64) = &snapshot.image_base64 else { return Ok(()) };
+        let rects = self
+            .browser
+            .element_rects(&self.selectors)
+            .await
+            .map_err(|e| AgentError::Other(e.to_string()))?;
+        if rects.is_empty() {
+            return Ok(());
+        }
+        let png = B64.decode(b64).map_err(|e| AgentError::Other(e.to_string()))?;
+        let mut img = crate::imaging::decode_png(&png).map_err(|e| AgentError::Other(e.to_string()))?;
+        for [x, y, w, h] in rects {
+            crate::imaging::fill_rect(&mut img, x, y, w, h);
+        }
+        let out = crate::imaging::encode_png(&img).map_err(|e| AgentError::Other(e.to_string()))?;
+        snapshot.image_base64 = Some(B64.encode(out));
+        Ok(())
+    }
 }
 
 // ========================= Chromium Adapter =========================
 
+/// Launches (or connects to) Chromium and owns the browser for the agent's lifetime.
 pub struct ChromiumComputer {
-    browser: Browser,
+    inner: BrowserComputer,
 }
 
 impl ChromiumComputer {
@@ -680,195 +2118,237 @@ impl ChromiumComputer {
         let browser = Browser::launch(cfg)
             .await
             .map_err(|e| AgentError::Other(e.to_string()))?;
-        Ok(Self { browser })
+        Ok(Self { inner: BrowserComputer::new(browser) })
     }
 
     pub async fn connect(ws_url: &str) -> Result<Self, AgentError> {
         let browser = Browser::connect(ws_url)
             .await
             .map_err(|e| AgentError::Other(e.to_string()))?;
-        Ok(Self { browser })
+        Ok(Self { inner: BrowserComputer::new(browser) })
     }
-}
 
-#[async_trait]
-impl Computer for ChromiumComputer {
-    async fn open_url(&self, url: &str) -> Result<Snapshot, AgentError> {
-        self.browser
-            .goto(url)
+    /// A sibling computer in a fresh, isolated browser context (own cookies and storage) of
+    /// the same Chromium process; cheaper than one process per account for parallel runs.
+    /// Keeps this computer's `min_screenshot_interval`.
+    pub async fn launch_isolated(&self) -> Result<Self, AgentError> {
+        let browser = self
+            .inner
+            .browser()
+            .new_incognito_context()
             .await
             .map_err(|e| AgentError::Other(e.to_string()))?;
-        // Ensure links open in same tab to keep control
-        let _ = self.browser.enable_single_tab_mode().await;
-        self.browser
-            .wait_for_stable()
-            .await
-            .map_err(|e| AgentError::Other(e.to_string()))?;
-        let snap_b64 = self
-            .browser
-            .screenshot_b64()
-            .await
-            .map_err(|e| AgentError::Other(e.to_string()))?;
-        Ok(Snapshot {
-            id: nanoid!(),
-            url: Some(url.to_string()),
-            title: None,
-            image_base64: Some(snap_b64),
-            dom_summary: None,
-            captured_at_ms: 0,
+        Ok(Self {
+            inner: BrowserComputer::new(browser).with_min_screenshot_interval(self.inner.min_screenshot_interval),
         })
     }
 
-    async fn snapshot(&self) -> Result<Snapshot, AgentError> {
-        let url = self
-            .browser
-            .url()
-            .await
-            .map_err(|e| AgentError::Other(e.to_string()))?;
-        let snap_b64 = self
-            .browser
-            .screenshot_b64()
+    /// Reuse the last screenshot for `snapshot` calls within `interval` of it, unless an
+    /// action changed the page or the URL moved since. Protects CDP from capture bursts.
+    pub fn with_min_screenshot_interval(mut self, interval: Duration) -> Self {
+        self.inner = self.inner.with_min_screenshot_interval(interval);
+        self
+    }
+
+    /// The page being driven, e.g. for a `SelectorRedactor`.
+    pub fn page(&self) -> chromiumoxide::Page {
+        self.inner.browser().page()
+    }
+
+    /// Install cookies and localStorage before the run navigates; `launch` already does this
+    /// for `BrowserConfig::session_seed`, so this is for connected or isolated computers.
+    pub async fn seed_session(&self, seed: &crate::browser::SessionSeed) -> Result<(), AgentError> {
+        self.inner
+            .browser()
+            .seed_session(seed)
             .await
-            .map_err(|e| AgentError::Other(e.to_string()))?;
-        Ok(Snapshot {
-            id: nanoid!(),
-            url: Some(url),
-            title: None,
-            image_base64: Some(snap_b64),
-            dom_summary: None,
-            captured_at_ms: 0,
-        })
+            .map_err(|e| AgentError::Other(e.to_string()))
     }
+}
 
-    async fn find(&self, locator: &Locator, _timeout: Duration) -> Result<DomNode, AgentError> {
-        Ok(DomNode {
-            locator: locator.clone(),
-            description: Some("chromium".to_string()),
-            rect: None,
-        })
+forward_computer!([] ChromiumComputer, inner);
+
+/// Drives a page owned by someone else (e.g. a larger automation app) over CDP.
+/// Dropping it leaves the browser and page untouched, and the page's `window.open` and
+/// `target=_blank` behaviour is never rewritten (no single-tab mode, no popup following).
+pub struct CdpComputer {
+    inner: BrowserComputer,
+}
+
+impl CdpComputer {
+    pub fn new(page: chromiumoxide::Page) -> Self {
+        Self { inner: BrowserComputer::new(Browser::from_page(page)) }
     }
 
-    async fn act(&self, action: &Action, _timeout: Duration) -> Result<ActionResult, AgentError> {
-        match action {
-            Action::NavGoto { url } => {
-                let _ = self.open_url(url).await?;
-            }
-            Action::Click { target } => {
-                match target {
-                    Locator::Coordinates { x, y } => {
-                        self.browser
-                            .click(*x as i64, *y as i64, "left")
-                            .await
-                            .map_err(|e| AgentError::Other(e.to_string()))?;
-                    }
-                    _ => {
-                        return Err(AgentError::Other(
-                            "click target type not implemented".into(),
-                        ));
-                    }
-                }
-            }
-            Action::Hover { target } => {
-                match target {
-                    Locator::Coordinates { x, y } => {
-                        self.browser
-                            .move_mouse(*x as i64, *y as i64)
-                            .await
-                            .map_err(|e| AgentError::Other(e.to_string()))?;
-                    }
-                    _ => {
-                        return Err(AgentError::Other(
-                            "hover target type not implemented".into(),
-                        ));
-                    }
-                }
-            }
-            Action::Scroll { target: None, dx, dy } => {
-                self.browser
-                    .scroll(*dx as i64, *dy as i64)
-                    .await
-                    .map_err(|e| AgentError::Other(e.to_string()))?;
-            }
-            Action::Key { combo } => {
-                self.browser
-                    .keypress(combo)
-                    .await
-                    .map_err(|e| AgentError::Other(e.to_string()))?;
-            }
-            Action::Type { text, .. } => {
-                self.browser
-                    .type_text(text)
-                    .await
-                    .map_err(|e| AgentError::Other(e.to_string()))?;
-            }
-            _ => {
-                return Err(AgentError::Other(
-                    "action not implemented in chromium adapter".into(),
-                ));
-            }
-        }
-        // Keep to same tab post-action as actions might trigger new tabs
-        let _ = self.browser.enable_single_tab_mode().await;
-        Ok(ActionResult {
-            snapshot: self.snapshot().await?,
-            changed: true,
-            message: None,
-        })
+    /// See `ChromiumComputer::with_min_screenshot_interval`.
+    pub fn with_min_screenshot_interval(mut self, interval: Duration) -> Self {
+        self.inner = self.inner.with_min_screenshot_interval(interval);
+        self
     }
 }
 
+forward_computer!([] CdpComputer, inner);
+
 // ========================= CUA-backed Reasoner =========================
 
+#[derive(Default)]
 struct CuaState {
     previous: Option<ResponseId>,
     pending_call_id: Option<String>,
-    pending_safety_checks: Vec<Value>,
+    pending_safety_checks: Vec<SafetyCheck>,
     awaiting_screenshot: bool,
     done_message: Option<String>,
-}
-
-impl Default for CuaState {
-    fn default() -> Self {
-        Self {
-            previous: None,
-            pending_call_id: None,
-            pending_safety_checks: Vec::new(),
-            awaiting_screenshot: false,
-            done_message: None,
-        }
-    }
+    last_image: Option<String>, // last screenshot sent, reused for calls that need no new one
+    image_scale: Option<f64>,   // page px per model px when the last image was downscaled
+    marker_seen: bool,          // a message contained `completion_marker`
+    turns: u32,                 // API requests made on this reasoner
+    turns_exhausted: bool,      // `max_turns` was reached and the run should finalize
+    usage: TokenUsage,          // summed over every request
+    queued: std::collections::VecDeque<CuaCall>, // rest of a batch, handed out without a request
+    answered: Vec<CallOutput>,  // outputs for earlier calls of the current batch
+    history: History,           // conversation resent on every request in `zdr` mode
 }
 
 #[derive(Clone, Debug)]
 pub struct CuaReasonerConfig {
     pub stop_on_message: bool,
+    pub stop_condition: StopCondition, // which messages end the run when `stop_on_message` is set
     pub auto_confirm_text: Option<String>,
+    pub max_image_bytes: Option<usize>, // reject screenshots whose decoded size exceeds this
+    /// Downscale screenshots so the longer side is at most this many pixels. Coordinates the
+    /// model returns are in the downscaled space and are scaled back before acting, so clicks
+    /// still land on the right element.
+    pub max_image_dimension: Option<u32>,
+    /// Text (e.g. "TASK_COMPLETE") that ends the run when any model message contains it,
+    /// regardless of `stop_on_message`. Tell the model to emit it in the instructions.
+    pub completion_marker: Option<String>,
+    /// Hard cap on API requests (turns and computer call outputs). Once reached, the next step
+    /// makes no request and `success()` returns true with "max turns reached", so a thread that
+    /// never converges still finalizes cleanly.
+    pub max_turns: Option<u32>,
+    /// Ask the model to move between controls with Tab/Shift+Tab and activate them with
+    /// Enter/Space rather than clicking coordinates; for pages whose focus order is more
+    /// reliable than their layout.
+    pub prefer_keyboard: bool,
+    /// For Zero Data Retention orgs, where `previous_response_id` is unavailable: keep the
+    /// conversation client-side and resend it with every request (`store: false`).
+    pub zdr: bool,
+    /// In `zdr` mode, the most screenshots resent per request; older turns are dropped oldest
+    /// first. Bounds payload growth and cost on long runs; see `cua::History` for how this
+    /// relates to `CuaConfig::truncation`. None resends the whole run.
+    pub max_history_images: Option<usize>,
 }
 
 impl Default for CuaReasonerConfig {
     fn default() -> Self {
-        Self { stop_on_message: true, auto_confirm_text: None }
+        Self {
+            stop_on_message: true,
+            stop_condition: StopCondition::AnyMessage,
+            auto_confirm_text: None,
+            max_image_bytes: Some(20 * 1024 * 1024),
+            max_image_dimension: None,
+            completion_marker: None,
+            max_turns: None,
+            prefer_keyboard: false,
+            zdr: false,
+            max_history_images: None,
+        }
     }
 }
 
+/// Decides whether a model message means the task is complete.
+#[derive(Clone, Debug, Default)]
+pub enum StopCondition {
+    /// Every message completes the run.
+    #[default]
+    AnyMessage,
+    /// Messages that read like a question (a trailing `?`, or a sentence opening with "Should
+    /// I", "Please confirm", ...) are clarifying questions, not completion; anything else
+    /// completes the run.
+    QuestionLike,
+    /// Returns true when the message completes the run.
+    Custom(fn(&str) -> bool),
+}
+
+impl StopCondition {
+    pub fn is_completion(&self, text: &str) -> bool {
+        match self {
+            StopCondition::AnyMessage => true,
+            StopCondition::QuestionLike => !Self::looks_like_question(text),
+            StopCondition::Custom(f) => f(text),
+        }
+    }
+
+    fn looks_like_question(text: &str) -> bool {
+        // Only as sentence openers: mid-sentence ("..., which is now in Drafts") they are
+        // ordinary completion text
+        const PHRASES: [&str; 6] = [
+            "should i ",
+            "would you like",
+            "do you want",
+            "can you confirm",
+            "please confirm",
+            "could you ",
+        ];
+        let t = text.trim();
+        if t.ends_with('?') {
+            return true;
+        }
+        let lower = t.to_lowercase();
+        lower
+            .split(['.', '!', '\n'])
+            .map(str::trim_start)
+            .any(|sentence| PHRASES.iter().any(|p| sentence.starts_with(p)))
+    }
+}
+
+/// Subdirectory of the system temp dir used by the default `DiskThreadStore`.
+const DEFAULT_THREADS_DIR: &str = "glass_hands_threads";
+
+const KEYBOARD_NAVIGATION_NOTE: &str = "\nPrefer keyboard navigation: move focus with Tab and Shift+Tab, \
+activate links and buttons with Enter or Space, and click coordinates only when the keyboard cannot reach a control.\n";
+
 #[derive(Clone)]
 pub struct CuaReasoner {
     client: CuaClient,
     instructions: String,
     state: std::sync::Arc<Mutex<CuaState>>,
     cfg: CuaReasonerConfig,
+    threads: Option<Arc<dyn ThreadStore>>, // optional sink for the response thread id
 }
 
 impl CuaReasoner {
     pub fn new(client: CuaClient, instructions: impl Into<String>) -> Self {
-        Self { client, instructions: instructions.into(), state: std::sync::Arc::new(Mutex::new(CuaState::default())), cfg: CuaReasonerConfig::default() }
+        Self::with_config(client, instructions, CuaReasonerConfig::default())
     }
 
     pub fn with_config(client: CuaClient, instructions: impl Into<String>, cfg: CuaReasonerConfig) -> Self {
-        Self { client, instructions: instructions.into(), state: std::sync::Arc::new(Mutex::new(CuaState::default())), cfg }
+        let state = CuaState { history: History::new(cfg.max_history_images), ..Default::default() };
+        let threads: Arc<dyn ThreadStore> = Arc::new(DiskThreadStore::new(std::env::temp_dir().join(DEFAULT_THREADS_DIR)));
+        Self {
+            client,
+            instructions: instructions.into(),
+            state: std::sync::Arc::new(Mutex::new(state)),
+            cfg,
+            threads: Some(threads),
+        }
+    }
+
+    /// Where the response thread id is persisted after every turn, so a crashed run re-run with
+    /// the same id (`Agent::run_goal_with_id`) continues its thread. Defaults to a
+    /// `DiskThreadStore` under the system temp dir.
+    pub fn with_thread_store(mut self, store: Arc<dyn ThreadStore>) -> Self {
+        self.threads = Some(store);
+        self
+    }
+
+    /// Keep the thread id in memory only.
+    pub fn without_thread_store(mut self) -> Self {
+        self.threads = None;
+        self
     }
 
-    fn compose_instructions(base: &str, goal: &Goal) -> String {
+    fn compose_instructions(base: &str, goal: &Goal, notes: &[String]) -> String {
         let mut s = String::new();
         if !base.trim().is_empty() {
             s.push_str(base);
@@ -892,17 +2372,88 @@ impl CuaReasoner {
                 s.push('\n');
             }
         }
+        if !notes.is_empty() {
+            s.push_str("\nNotes:\n");
+            for n in notes {
+                s.push_str(n);
+                s.push('\n');
+            }
+        }
         s
     }
 
+    /// Downscale per `max_image_dimension`; returns the image to send and the scale applied.
+    fn prepare_image(&self, b64: String) -> Result<(String, Option<f64>), AgentError> {
+        let Some(max_dim) = self.cfg.max_image_dimension else { return Ok((b64, None)) };
+        let map = |e: anyhow::Error| AgentError::Reasoner(format!("downscale: {}", e));
+        let png = B64.decode(&b64).map_err(|e| AgentError::Reasoner(format!("b64 decode: {}", e)))?;
+        let img = crate::imaging::decode_png(&png).map_err(map)?;
+        match crate::imaging::downscale(&img, max_dim) {
+            Some(small) => {
+                let scale = img.width as f64 / small.width as f64;
+                let out = crate::imaging::encode_png(&small).map_err(map)?;
+                Ok((B64.encode(out), Some(scale)))
+            }
+            None => Ok((b64, None)),
+        }
+    }
+
+    /// Queue a batch of calls and hand out the first; `step` answers each with the screenshot
+    /// taken after it and sends all outputs together once the queue is empty.
+    fn start_batch(st: &mut CuaState, calls: Vec<CuaCall>) -> Thought {
+        info!(calls = calls.len(), "CUA returned a batch of computer calls");
+        st.queued = calls.into();
+        st.answered.clear();
+        match st.queued.pop_front() {
+            Some(first) => Self::take_call(st, first),
+            None => Thought::default(),
+        }
+    }
+
+    fn take_call(st: &mut CuaState, call: CuaCall) -> Thought {
+        st.pending_call_id = Some(call.call_id);
+        st.pending_safety_checks = call.safety_checks;
+        st.awaiting_screenshot = call.requires_screenshot;
+        Self::action_thought(call.action, call.requires_screenshot, st.image_scale)
+    }
+
+    fn action_thought(action: CuaAction, requires_screenshot: bool, scale: Option<f64>) -> Thought {
+        let unmapped_action = match &action {
+            CuaAction::Unknown(raw) => Some(raw.clone()),
+            _ => None,
+        };
+        let action = match scale {
+            Some(f) => Self::scale_cua_action(action, f),
+            None => action,
+        };
+        Thought { action: Self::map_cua_action(action), unmapped_action, requires_screenshot, ..Default::default() }
+    }
+
+    /// Map coordinates from the downscaled image the model saw back to page pixels.
+    fn scale_cua_action(action: CuaAction, f: f64) -> CuaAction {
+        let s = |v: i64| (v as f64 * f).round() as i64;
+        match action {
+            CuaAction::Click { x, y, button } => CuaAction::Click { x: s(x), y: s(y), button },
+            CuaAction::DoubleClick { x, y } => CuaAction::DoubleClick { x: s(x), y: s(y) },
+            CuaAction::Move { x, y } => CuaAction::Move { x: s(x), y: s(y) },
+            CuaAction::Scroll { dx, dy } => CuaAction::Scroll { dx: s(dx), dy: s(dy) },
+            CuaAction::DragPath { points } => CuaAction::DragPath { points: points.into_iter().map(|(x, y)| (s(x), s(y))).collect() },
+            other => other,
+        }
+    }
+
     fn map_cua_action(action: CuaAction) -> Option<Action> {
         match action {
-            CuaAction::Click { x, y, .. } => Some(Action::Click { target: Locator::Coordinates { x: x as i32, y: y as i32 } }),
-            CuaAction::DoubleClick { x, y } => Some(Action::Click { target: Locator::Coordinates { x: x as i32, y: y as i32 } }),
+            CuaAction::Click { x, y, button } => Some(Action::Click { target: Locator::Coordinates { x: x as i32, y: y as i32 }, button }),
+            CuaAction::DoubleClick { x, y } => Some(Action::Click { target: Locator::Coordinates { x: x as i32, y: y as i32 }, button: None }),
             CuaAction::Move { x, y } => Some(Action::Hover { target: Locator::Coordinates { x: x as i32, y: y as i32 } }),
             CuaAction::Scroll { dx, dy } => Some(Action::Scroll { target: None, dx: dx as i32, dy: dy as i32 }),
-            CuaAction::Type { text } => Some(Action::Type { text, into: Locator::Css { selector: "*".to_string() } }),
+            CuaAction::Type { text } => Some(Action::Type { text, into: Locator::Css { selector: "*".to_string() }, clear: false }),
             CuaAction::Keypress { key } => Some(Action::Key { combo: key }),
+            CuaAction::Back => Some(Action::NavBack),
+            CuaAction::Forward => Some(Action::NavForward),
+            CuaAction::Reload => Some(Action::Reload),
+            CuaAction::Goto { url } => Some(Action::NavGoto { url }),
             CuaAction::WaitMs { .. } => None,
             CuaAction::DragPath { .. } => None,
             CuaAction::Screenshot => None,
@@ -916,32 +2467,260 @@ impl Reasoner for CuaReasoner {
     async fn think(
         &self,
         goal: &Goal,
-        _memory: &Memory,
+        memory: &Memory,
         snapshot: &Snapshot,
-        _last_error: Option<&AgentError>,
+        last_error: Option<&AgentError>,
     ) -> Result<Thought, AgentError> {
         let mut st = self.state.lock().await;
+        let before = st.previous.as_ref().map(|r| r.0.clone());
+        let thought = self.step(goal, snapshot, memory, last_error, &mut st).await?;
+        self.save_thread(&memory.run_id, before, &st).await;
+        Ok(thought)
+    }
 
-        // If we are awaiting to send a screenshot for a prior computer_call
-        if st.awaiting_screenshot {
-            let b64 = snapshot
-                .image_base64
-                .clone()
-                .ok_or_else(|| AgentError::Reasoner("missing snapshot image".into()))?;
+    async fn success(
+        &self,
+        _goal: &Goal,
+        _snapshot: &Snapshot,
+        _memory: &Memory,
+    ) -> Result<bool, AgentError> {
+        let st = self.state.lock().await;
+        Ok(st.turns_exhausted || st.marker_seen || (self.cfg.stop_on_message && st.done_message.is_some()))
+    }
+
+    /// Show the model the page as it is now and ask whether the goal is met. Anything but a
+    /// "YES" withdraws the completion; calls it makes instead are handed out by the next `think`.
+    async fn confirm_success(
+        &self,
+        _goal: &Goal,
+        snapshot: &Snapshot,
+        memory: &Memory,
+    ) -> Result<bool, AgentError> {
+        let mut st = self.state.lock().await;
+        if st.turns_exhausted {
+            return Ok(true);
+        }
+        if self.cfg.max_turns.is_some_and(|max| st.turns >= max) {
+            // No request left to confirm with; end as `step` would
+            st.turns_exhausted = true;
+            return Ok(true);
+        }
+        let before = st.previous.as_ref().map(|r| r.0.clone());
+        let confirmed = self.verify_turn(snapshot, &mut st).await;
+        self.save_thread(&memory.run_id, before, &st).await;
+        let confirmed = confirmed?;
+        if !confirmed {
+            st.done_message = None;
+            st.marker_seen = false;
+        }
+        Ok(confirmed)
+    }
+
+    async fn usage(&self) -> Option<TokenUsage> {
+        Some(self.state.lock().await.usage)
+    }
+
+    /// Continue the response thread stored for `run_id`, if any. ZDR mode keeps no server-side
+    /// thread, so there is nothing to continue.
+    async fn resume(&self, run_id: &str) -> Result<bool, AgentError> {
+        let Some(store) = &self.threads else { return Ok(false) };
+        if self.cfg.zdr {
+            return Ok(false);
+        }
+        match store.load_thread(run_id).await? {
+            Some(id) => {
+                info!(run_id = %run_id, response_id = %id.0, "resuming CUA thread");
+                self.state.lock().await.previous = Some(id);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+const VERIFY_SUCCESS_PROMPT: &str = "Before this task is closed, check the attached screenshot of the page as it is now. \
+Reply with exactly YES if the goal has been fully achieved. Otherwise reply NO with what is missing, or continue working on it.";
+
+impl CuaReasoner {
+    /// Persist the thread id if the last request moved it.
+    async fn save_thread(&self, run_id: &str, before: Option<String>, st: &CuaState) {
+        if let (Some(store), Some(id)) = (&self.threads, &st.previous) {
+            if before.as_deref() != Some(id.0.as_str()) {
+                if let Err(e) = store.save_thread(run_id, id).await {
+                    warn!("thread save failed: {}", e);
+                }
+            }
+        }
+    }
+
+    /// One verification turn for `confirm_success`; true when the model answers YES.
+    async fn verify_turn(&self, snapshot: &Snapshot, st: &mut CuaState) -> Result<bool, AgentError> {
+        let b64 = snapshot
+            .image_base64
+            .clone()
+            .ok_or_else(|| AgentError::Reasoner("missing snapshot image".into()))?;
+        let (b64, scale) = self.prepare_image(b64)?;
+        let input = crate::cua::TurnInput {
+            instructions: VERIFY_SUCCESS_PROMPT.into(),
+            current_url: snapshot.url.clone(),
+            extra_user_text: None,
+            image_base64: Some(b64.clone()),
+        };
+        st.turns += 1;
+        let sent = if self.cfg.zdr {
+            self.client.turn_in_history(input, &mut st.history).await
+        } else {
+            self.client.turn_with_usage(input, st.previous.as_ref()).await
+        };
+        let (out, usage) = sent.map_err(|e| AgentError::Reasoner(e.to_string()))?;
+        st.usage.add(usage);
+        st.last_image = Some(b64);
+        st.image_scale = scale;
+        match out {
+            CuaOutput::Message { text } => {
+                let confirmed = text.trim_start().to_ascii_uppercase().starts_with("YES");
+                if !confirmed {
+                    info!("CUA did not confirm completion: {}", text);
+                }
+                Ok(confirmed)
+            }
+            CuaOutput::Done { response_id } => {
+                st.previous = Some(response_id);
+                Ok(true)
+            }
+            CuaOutput::ComputerCall { call_id, action, requires_screenshot, response_id, safety_checks } => {
+                // The model is still working; its call waits for the next `think`
+                st.previous = Some(response_id);
+                st.queued = std::collections::VecDeque::from([CuaCall { call_id, action, requires_screenshot, safety_checks }]);
+                st.answered.clear();
+                Ok(false)
+            }
+            CuaOutput::Batch { calls, response_id } => {
+                st.previous = Some(response_id);
+                st.queued = calls.into();
+                st.answered.clear();
+                Ok(false)
+            }
+            CuaOutput::Refusal { reason } => Err(AgentError::Refused(reason)),
+        }
+    }
+
+    /// Record whether a model message ends the run.
+    fn note_message(&self, st: &mut CuaState, text: &str) {
+        if self.cfg.stop_on_message && self.cfg.stop_condition.is_completion(text) {
+            st.done_message = Some(text.to_string());
+        }
+        if let Some(marker) = &self.cfg.completion_marker {
+            if text.contains(marker.as_str()) {
+                st.marker_seen = true;
+            }
+        }
+    }
+
+    async fn step(
+        &self,
+        goal: &Goal,
+        snapshot: &Snapshot,
+        memory: &Memory,
+        last_error: Option<&AgentError>,
+        st: &mut CuaState,
+    ) -> Result<Thought, AgentError> {
+        if self.cfg.max_turns.is_some_and(|max| st.turns >= max) {
+            warn!(turns = st.turns, "CUA max turns reached");
+            st.turns_exhausted = true;
+            st.done_message = Some("max turns reached".into());
+            return Ok(Thought { plan: "max turns reached".into(), ..Default::default() });
+        }
+        if st.pending_call_id.is_none() {
+            // Calls the model made in reply to a `confirm_success` check
+            if let Some(next) = st.queued.pop_front() {
+                return Ok(Self::take_call(st, next));
+            }
+        }
+        let hint = memory.hint.as_deref();
+        // Every computer_call needs an output. A fresh image is mandatory only when the model
+        // asked for one; otherwise the last image we sent keeps the thread consistent.
+        let pending_image = if st.awaiting_screenshot {
+            Some(
+                snapshot
+                    .image_base64
+                    .clone()
+                    .ok_or_else(|| AgentError::Reasoner("missing snapshot image".into()))?,
+            )
+        } else if st.pending_call_id.is_some() {
+            snapshot.image_base64.clone().or_else(|| st.last_image.clone())
+        } else {
+            None
+        };
+        if let Some(b64) = pending_image {
+            let (b64, scale) = self.prepare_image(b64)?;
+            if let Some(limit) = self.cfg.max_image_bytes {
+                // Decoded size of a base64 payload, without decoding it
+                let bytes = b64.len() / 4 * 3;
+                if bytes > limit {
+                    return Err(AgentError::Reasoner(format!("screenshot too large: {} bytes", bytes)));
+                }
+            }
             let call_id = st
                 .pending_call_id
                 .clone()
                 .ok_or_else(|| AgentError::Reasoner("missing call_id".into()))?;
-            let resp = self
-                .client
-                .send_computer_output(
-                    &call_id,
-                    CuaToolImage { r#type: "input_image".into(), mime_type: "image/png".into(), data_base64: b64 },
-                    st.previous.as_ref(),
-                    Some(&st.pending_safety_checks),
-                )
-                .await
-                .map_err(|e| AgentError::Reasoner(e.to_string()))?;
+            st.last_image = Some(b64.clone());
+            let output = CallOutput {
+                call_id,
+                image: CuaToolImage { r#type: "input_image".into(), mime_type: "image/png".into(), data_base64: b64 },
+                acknowledged_safety_checks: std::mem::take(&mut st.pending_safety_checks),
+            };
+            let mut skipped = Vec::new();
+            let mut skipped_note = None;
+            if let Some(err) = last_error.filter(|_| !st.queued.is_empty()) {
+                // The agent denied or failed this call, so the rest of the batch is moot. Every
+                // call still needs an output: answer the skipped ones with the same image and
+                // tell the model why nothing happened.
+                let calls = std::mem::take(&mut st.queued);
+                warn!(skipped = calls.len(), "abandoning CUA batch after a failed call: {}", err);
+                skipped_note = Some(format!(
+                    "Your last action was not completed ({}), so the {} action(s) after it in that batch were skipped. \
+                     Look at the current screen and plan again.",
+                    err,
+                    calls.len()
+                ));
+                skipped = calls
+                    .into_iter()
+                    .map(|call| CallOutput {
+                        call_id: call.call_id,
+                        image: CuaToolImage {
+                            r#type: "input_image".into(),
+                            mime_type: "image/png".into(),
+                            data_base64: output.image.data_base64.clone(),
+                        },
+                        acknowledged_safety_checks: Vec::new(), // never performed, so nothing to acknowledge
+                    })
+                    .collect();
+            }
+            if let Some(next) = st.queued.pop_front() {
+                // Mid-batch: keep this output for the combined reply and hand out the next call.
+                // Its coordinates refer to the image the batch was issued on, so the scale stays.
+                st.answered.push(output);
+                return Ok(Self::take_call(st, next));
+            }
+            st.image_scale = scale;
+            let mut outputs = std::mem::take(&mut st.answered);
+            outputs.push(output);
+            outputs.extend(skipped);
+            let note = match (skipped_note, hint) {
+                (Some(n), Some(h)) => Some(format!("{}\n{}", n, h)),
+                (n, h) => n.or_else(|| h.map(str::to_string)),
+            };
+            let hint = note.as_deref();
+            st.turns += 1;
+            let sent = if self.cfg.zdr {
+                self.client.send_computer_outputs_in_history(outputs, hint, &mut st.history).await
+            } else {
+                self.client.send_computer_outputs_with_usage(outputs, st.previous.as_ref(), hint).await
+            };
+            let (resp, usage) = sent.map_err(|e| AgentError::Reasoner(e.to_string()))?;
+            st.usage.add(usage);
 
             match resp {
                 CuaOutput::Message { text } => {
@@ -949,18 +2728,19 @@ impl Reasoner for CuaReasoner {
                     st.pending_call_id = None;
                     st.pending_safety_checks.clear();
                     st.awaiting_screenshot = false;
-                    if self.cfg.stop_on_message {
-                        st.done_message = Some(text.clone());
-                    }
-                    return Ok(Thought { plan: text, action: None, rationale: None });
+                    self.note_message(st, &text);
+                    return Ok(Thought { plan: text, ..Default::default() });
                 }
                 CuaOutput::ComputerCall { call_id, action, requires_screenshot, response_id, safety_checks } => {
                     st.previous = Some(response_id);
                     st.pending_call_id = Some(call_id);
                     st.pending_safety_checks = safety_checks;
                     st.awaiting_screenshot = requires_screenshot;
-                    let mapped = Self::map_cua_action(action);
-                    return Ok(Thought { plan: String::new(), action: mapped, rationale: None });
+                    return Ok(Self::action_thought(action, requires_screenshot, st.image_scale));
+                }
+                CuaOutput::Batch { calls, response_id } => {
+                    st.previous = Some(response_id);
+                    return Ok(Self::start_batch(st, calls));
                 }
                 CuaOutput::Done { response_id } => {
                     st.previous = Some(response_id);
@@ -968,57 +2748,246 @@ impl Reasoner for CuaReasoner {
                     st.pending_safety_checks.clear();
                     st.awaiting_screenshot = false;
                     st.done_message = Some("done".into());
-                    return Ok(Thought { plan: "done".into(), action: None, rationale: None });
+                    return Ok(Thought { plan: "done".into(), ..Default::default() });
+                }
+                CuaOutput::Refusal { reason } => {
+                    st.pending_call_id = None;
+                    st.pending_safety_checks.clear();
+                    st.awaiting_screenshot = false;
+                    return Err(AgentError::Refused(reason));
                 }
             }
         }
 
         // Start or continue a turn
-        let composed = Self::compose_instructions(&self.instructions, goal);
+        let mut composed = Self::compose_instructions(&self.instructions, goal, &memory.notes);
+        if self.cfg.prefer_keyboard {
+            composed.push_str(KEYBOARD_NAVIGATION_NOTE);
+        }
+        // A ZDR history always keeps its opening turn (see `History`); when that turn already
+        // carries these instructions, resending them would only repeat them
+        let opening = st.history.items().first().and_then(|i| i.pointer("/content/0/text")).and_then(|t| t.as_str());
+        if self.cfg.zdr && opening == Some(composed.as_str()) {
+            composed.clear();
+        }
         // Only append extra_user_text when not mid-thread to avoid tool-output expectation mismatches
         let extra = if st.previous.is_none() { self.cfg.auto_confirm_text.clone() } else { None };
-        let input = crate::cua::TurnInput { instructions: composed, current_url: snapshot.url.clone(), extra_user_text: extra };
-        let out = self
-            .client
-            .turn(input, st.previous.as_ref())
-            .await
-            .map_err(|e| AgentError::Reasoner(e.to_string()))?;
+        // No call is pending here, so an operator hint is safe to add even mid-thread
+        let extra = match (extra, hint) {
+            (Some(e), Some(h)) => Some(format!("{}\n{}", e, h)),
+            (e, h) => e.or_else(|| h.map(str::to_string)),
+        };
+        let input = crate::cua::TurnInput {
+            instructions: composed,
+            current_url: snapshot.url.clone(),
+            extra_user_text: extra,
+            image_base64: None,
+        };
+        st.turns += 1;
+        let sent = if self.cfg.zdr {
+            self.client.turn_in_history(input, &mut st.history).await
+        } else {
+            self.client.turn_with_usage(input, st.previous.as_ref()).await
+        };
+        let (out, usage) = sent.map_err(|e| AgentError::Reasoner(e.to_string()))?;
+        st.usage.add(usage);
 
         match out {
             CuaOutput::Message { text } => {
                 st.previous = st.previous.take();
-                if self.cfg.stop_on_message {
-                    st.done_message = Some(text.clone());
-                }
-                Ok(Thought { plan: text, action: None, rationale: None })
+                st.pending_call_id = None;
+                self.note_message(st, &text);
+                Ok(Thought { plan: text, ..Default::default() })
             }
             CuaOutput::ComputerCall { call_id, action, requires_screenshot, response_id, safety_checks } => {
                 st.previous = Some(response_id);
                 st.pending_call_id = Some(call_id);
                 st.pending_safety_checks = safety_checks;
                 st.awaiting_screenshot = requires_screenshot;
-                let mapped = Self::map_cua_action(action);
-                Ok(Thought { plan: String::new(), action: mapped, rationale: None })
+                Ok(Self::action_thought(action, requires_screenshot, st.image_scale))
+            }
+            CuaOutput::Batch { calls, response_id } => {
+                st.previous = Some(response_id);
+                Ok(Self::start_batch(st, calls))
             }
             CuaOutput::Done { response_id } => {
                 st.previous = Some(response_id);
+                st.pending_call_id = None;
                 st.done_message = Some("done".into());
-                Ok(Thought { plan: "done".into(), action: None, rationale: None })
+                Ok(Thought { plan: "done".into(), ..Default::default() })
+            }
+            CuaOutput::Refusal { reason } => {
+                st.pending_call_id = None;
+                Err(AgentError::Refused(reason))
             }
         }
     }
+}
 
-    async fn success(
-        &self,
-        _goal: &Goal,
-        _snapshot: &Snapshot,
-        _memory: &Memory,
-    ) -> Result<bool, AgentError> {
-        let st = self.state.lock().await;
-        if self.cfg.stop_on_message {
-            Ok(st.done_message.is_some())
-        } else {
-            Ok(false)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn question_like_stops_only_on_completions() {
+        let stop = StopCondition::QuestionLike;
+        for question in [
+            "Which account should I use?",
+            "I found two invoices. Should I download both",
+            "Please confirm the shipping address before I place the order.",
+            "The cart is ready.\nDo you want me to check out",
+            "could you share the 2FA code",
+        ] {
+            assert!(!stop.is_completion(question), "{question:?} read as a completion");
         }
+        for done in [
+            "Saved the invoice, which is now in Drafts.",
+            "Downloaded the PDF. Let me know if you need anything else.",
+            "The page asked whether I should continue, so I did.",
+            "Done!",
+        ] {
+            assert!(stop.is_completion(done), "{done:?} read as a question");
+        }
+    }
+
+    #[test]
+    fn new_id_rejects_unusable_settings() {
+        let cfg = AgentConfig { id_len: 8, id_alphabet: Some("ab".into()), ..Default::default() };
+        let id = cfg.new_id().expect("id");
+        assert_eq!(id.len(), 8);
+        assert!(id.chars().all(|c| c == 'a' || c == 'b'));
+
+        assert!(AgentConfig { id_len: 0, ..Default::default() }.new_id().is_err());
+        assert!(AgentConfig { id_alphabet: Some(String::new()), ..Default::default() }.new_id().is_err());
+    }
+
+    #[derive(Clone, Copy)]
+    struct HangingComputer;
+
+    #[async_trait]
+    impl Computer for HangingComputer {
+        async fn open_url(&self, _url: &str) -> Result<Snapshot, AgentError> {
+            std::future::pending().await
+        }
+
+        async fn snapshot(&self) -> Result<Snapshot, AgentError> {
+            NoopComputer.snapshot().await
+        }
+
+        async fn find(&self, locator: &Locator, timeout: Duration) -> Result<DomNode, AgentError> {
+            NoopComputer.find(locator, timeout).await
+        }
+
+        async fn act(&self, action: &Action, timeout: Duration) -> Result<ActionResult, AgentError> {
+            NoopComputer.act(action, timeout).await
+        }
+    }
+
+    #[tokio::test]
+    async fn deadline_covers_the_start_page() {
+        let agent = Agent::with_defaults(HangingComputer, SimpleReasoner, AgentConfig::default());
+        let goal = Goal { task: "go".into(), constraints: vec![], success_criteria: vec![], timeout_ms: Some(50) };
+        let report = tokio::time::timeout(Duration::from_secs(5), agent.run_goal(goal, Some("https://example.test")))
+            .await
+            .expect("run outlived its deadline")
+            .expect("report");
+        assert!(matches!(report.status, RunStatus::Timeout));
+    }
+
+    #[tokio::test]
+    async fn snapshots_are_reidded_unless_replayed() {
+        let cfg = AgentConfig { id_len: 6, id_alphabet: Some("x".into()), ..Default::default() };
+        let live = Agent::with_defaults(NoopComputer, SimpleReasoner, cfg.clone());
+        let mut snap = NoopComputer.snapshot().await.expect("snapshot");
+        live.prepare_snapshot(&mut snap).await.expect("prepare");
+        assert_eq!(snap.id, "xxxxxx");
+
+        let replay = Agent::with_defaults(ReplayComputer::new(vec![]), SimpleReasoner, cfg);
+        let mut snap = Snapshot::new(None, None);
+        let recorded = snap.id.clone();
+        replay.prepare_snapshot(&mut snap).await.expect("prepare");
+        assert_eq!(snap.id, recorded);
+    }
+
+    #[test]
+    fn save_pdf_paths_stay_in_the_artifacts_dir() {
+        let pdf = |path: &str| Action::SavePdf { path: path.into() };
+        let bare = Agent::with_defaults(NoopComputer, SimpleReasoner, AgentConfig::default());
+        assert!(bare.in_artifacts_dir(pdf("invoice.pdf"), "run").is_err());
+
+        let agent = bare.with_artifacts_dir("/tmp/artifacts");
+        match agent.in_artifacts_dir(pdf("out/invoice.pdf"), "run") {
+            Ok(Action::SavePdf { path }) => assert_eq!(Path::new(&path), Path::new("/tmp/artifacts/run/out/invoice.pdf")),
+            other => panic!("unexpected {other:?}"),
+        }
+        for escape in ["/etc/invoice.pdf", "../invoice.pdf", "out/../../invoice.pdf", ""] {
+            assert!(agent.in_artifacts_dir(pdf(escape), "run").is_err(), "{escape:?} accepted");
+        }
+        assert!(matches!(agent.in_artifacts_dir(Action::NavBack, "run"), Ok(Action::NavBack)));
+    }
+
+    #[test]
+    fn grouped_counts_derive_from_by_kind() {
+        let mut m = RunMetrics::default();
+        for kind in [ActionKind::Click, ActionKind::ClickInView, ActionKind::SetValue, ActionKind::Reload, ActionKind::Scroll] {
+            m.record(kind);
+        }
+        m.record(ActionKind::Click);
+        assert_eq!((m.clicks(), m.types(), m.navigations()), (3, 1, 1));
+        assert_eq!(m.by_kind.get(&ActionKind::Scroll), Some(&1));
+    }
+
+    #[test]
+    fn scale_cua_action_maps_model_pixels_to_page_pixels() {
+        match CuaReasoner::scale_cua_action(CuaAction::Click { x: 10, y: 21, button: None }, 2.5) {
+            CuaAction::Click { x, y, .. } => assert_eq!((x, y), (25, 53)),
+            other => panic!("unexpected {other:?}"),
+        }
+        match CuaReasoner::scale_cua_action(CuaAction::DragPath { points: vec![(1, 2), (3, 4)] }, 2.0) {
+            CuaAction::DragPath { points } => assert_eq!(points, vec![(2, 4), (6, 8)]),
+            other => panic!("unexpected {other:?}"),
+        }
+        match CuaReasoner::scale_cua_action(CuaAction::Type { text: "12".into() }, 2.0) {
+            CuaAction::Type { text } => assert_eq!(text, "12"),
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn action_thought_scales_coordinates_back() {
+        let thought = CuaReasoner::action_thought(CuaAction::Move { x: 100, y: 50 }, true, Some(2.0));
+        match thought.action {
+            Some(Action::Hover { target: Locator::Coordinates { x, y } }) => assert_eq!((x, y), (200, 100)),
+            other => panic!("unexpected {other:?}"),
+        }
+        let unscaled = CuaReasoner::action_thought(CuaAction::Move { x: 100, y: 50 }, true, None);
+        assert!(matches!(unscaled.action, Some(Action::Hover { target: Locator::Coordinates { x: 100, y: 50 } })));
+    }
+
+    fn png_b64(shade: u8) -> String {
+        let img = crate::imaging::Rgba { width: 16, height: 16, data: [shade, shade, shade, 255].repeat(256) };
+        B64.encode(crate::imaging::encode_png(&img).expect("encode"))
+    }
+
+    #[test]
+    fn content_eq_ignores_ids_and_capture_times() {
+        let a = Snapshot::new(Some("https://example.com/".into()), Some(png_b64(200)));
+        let mut b = Snapshot::new(Some("https://example.com/".into()), Some(png_b64(200)));
+        b.captured_at_ms += 5_000;
+        assert_ne!(a.id, b.id);
+        assert!(a.content_eq(&b));
+
+        let moved = Snapshot::new(Some("https://example.com/next".into()), Some(png_b64(200)));
+        assert!(!a.content_eq(&moved));
+        let without_image = Snapshot::new(Some("https://example.com/".into()), None);
+        assert!(!a.content_eq(&without_image));
+    }
+
+    #[test]
+    fn any_message_and_custom_conditions() {
+        assert!(StopCondition::AnyMessage.is_completion("Should I continue?"));
+        let custom = StopCondition::Custom(|t| t.contains("DONE"));
+        assert!(custom.is_completion("all DONE"));
+        assert!(!custom.is_completion("working"));
     }
 }
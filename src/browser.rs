@@ -1,36 +1,323 @@
+use crate::dom::{DomNode, DomRect, InteractiveElement, Locator};
 use anyhow::Result;
 use base64::{engine::general_purpose::STANDARD, Engine};
 use chromiumoxide::browser::Browser as OxideBrowser;
-use chromiumoxide::cdp::js_protocol::runtime::EvaluateParams;
-use chromiumoxide::cdp::browser_protocol::emulation::SetDeviceMetricsOverrideParams;
+use chromiumoxide::cdp::js_protocol::runtime::{
+    EvaluateParams, EventConsoleApiCalled, EventExceptionThrown, RemoteObject,
+};
+use chromiumoxide::cdp::browser_protocol::emulation::{
+    SetDeviceMetricsOverrideParams, SetUserAgentOverrideParams, UserAgentBrandVersion,
+    UserAgentMetadata,
+};
+use chromiumoxide::cdp::browser_protocol::dom::{FocusParams, SetFileInputFilesParams};
+use chromiumoxide::cdp::browser_protocol::browser::CloseParams;
+use chromiumoxide::cdp::browser_protocol::page::PrintToPdfParams;
+use chromiumoxide::cdp::browser_protocol::network::{
+    CookieParam, EventLoadingFailed, EventLoadingFinished, EventRequestWillBeSent, EventResponseReceived,
+    SetCookiesParams,
+};
+use chromiumoxide::cdp::browser_protocol::target::{CreateBrowserContextParams, CreateTargetParams, GetTargetsParams};
 use chromiumoxide::cdp::browser_protocol::input::{
-    DispatchMouseEventParams, DispatchMouseEventType, MouseButton,
+    DispatchKeyEventParams, DispatchKeyEventType, DispatchMouseEventParams, DispatchMouseEventType, MouseButton,
 };
 use chromiumoxide::layout::Point;
 use chromiumoxide::page::{Page};
 use futures::StreamExt;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::PathBuf;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 use tokio::time::sleep;
 
 #[derive(Clone)]
 pub struct BrowserConfig {
     pub headless: bool,
     pub user_agent: Option<String>,
+    pub ua_metadata: Option<UaMetadata>,
+    pub extra_args: Vec<String>, // appended to the Chromium command line, e.g. "--disable-gpu"
+    pub headless_mode: HeadlessMode, // only used when `headless` is true
+    pub remote_debugging_port: Option<u16>, // fixed port so external tools can attach
+    pub mask_password_fields: bool, // paint over input[type=password] in every screenshot
+    pub settle: SettleConfig,
+    /// Capture with a transparent background. Off by default: transparent pixels render as
+    /// black in some image pipelines, so pages without an explicit background would look
+    /// blank to the model and in saved PNGs.
+    pub omit_background: bool,
+    pub screenshot_retries: u32,          // extra attempts when a capture fails on a 0x0 page
+    pub screenshot_retry_delay: Duration, // wait between those attempts
+    pub session_seed: Option<SessionSeed>, // applied to each page this crate opens, before any navigation
+    /// Draw numbered boxes over interactive elements in every screenshot (set-of-marks), so a
+    /// vision model can answer "click 7"; act on it with `Locator::Ref { id: "7" }`.
+    pub annotate_elements: bool,
+    /// What `dismiss_common_banners` clicks; extend the defaults for site-specific banners.
+    pub consent: ConsentConfig,
+    /// Keep everything in one tab by rewriting `window.open` and `target=_blank` links (the
+    /// default). Turn off for flows that need real popups (OAuth, PDFs in a new tab); the
+    /// adapter then follows whichever tab an action opened and returns when it closes.
+    pub single_tab_mode: bool,
+    /// Where element clicks land, as fractions of the element's box: (0.5, 0.5) is the center.
+    /// When another element covers that point, `click_point` tries a few other spots.
+    pub click_offset: (f64, f64),
+    pub click_check: ClickCheck, // probe what lies under coordinate clicks
+    /// CSS of a form whose `form_state` is attached to every snapshot, so a reasoner can
+    /// check what it has entered without reading the screenshot. Off by default.
+    pub snapshot_form: Option<String>,
 }
 
 impl Default for BrowserConfig {
     fn default() -> Self {
-        Self { headless: true, user_agent: None }
+        Self {
+            headless: true,
+            user_agent: None,
+            ua_metadata: None,
+            extra_args: Vec::new(),
+            headless_mode: HeadlessMode::default(),
+            remote_debugging_port: None,
+            mask_password_fields: false,
+            settle: SettleConfig::default(),
+            omit_background: false,
+            screenshot_retries: 1,
+            screenshot_retry_delay: Duration::from_millis(50),
+            session_seed: None,
+            annotate_elements: false,
+            consent: ConsentConfig::default(),
+            single_tab_mode: true,
+            click_offset: (0.5, 0.5),
+            click_check: ClickCheck::default(),
+            snapshot_form: None,
+        }
+    }
+}
+
+/// Rendering options for `Browser::print_to_pdf`.
+#[derive(Clone, Copy, Debug)]
+pub struct PdfOptions {
+    pub landscape: bool,
+    pub scale: f64,             // 0.1 to 2.0
+    pub print_background: bool, // include background colors and images
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self { landscape: false, scale: 1.0, print_background: true }
+    }
+}
+
+/// Consent/cookie banner buttons `Browser::dismiss_common_banners` looks for: CSS selectors
+/// first, then buttons and links whose whole text matches (case-insensitive).
+#[derive(Clone, Debug)]
+pub struct ConsentConfig {
+    pub selectors: Vec<String>,
+    pub texts: Vec<String>,
+}
+
+impl Default for ConsentConfig {
+    fn default() -> Self {
+        let selectors = [
+            "#onetrust-accept-btn-handler",                             // OneTrust
+            "#didomi-notice-agree-button",                              // Didomi
+            "#CybotCookiebotDialogBodyLevelButtonLevelOptinAllowAll",   // Cookiebot
+            "#CybotCookiebotDialogBodyButtonAccept",
+            "#truste-consent-button",                                   // TrustArc
+            ".qc-cmp2-summary-buttons button[mode=primary]",            // Quantcast
+            ".fc-cta-consent",                                          // Google Funding Choices
+            "button#L2AGLb",                                            // Google search
+            "[data-testid=uc-accept-all-button]",                       // Usercentrics
+        ];
+        let texts = [
+            "Accept all", "Accept all cookies", "Accept cookies", "Allow all", "Allow all cookies",
+            "I agree", "I accept", "Agree", "Agree and close", "Got it",
+        ];
+        Self {
+            selectors: selectors.iter().map(|s| s.to_string()).collect(),
+            texts: texts.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+/// Cookies and localStorage to put in place before the first navigation, so a run can start
+/// already signed in instead of scripting the login every time.
+#[derive(Clone, Debug, Default)]
+pub struct SessionSeed {
+    pub cookies: Vec<CookieParam>, // each needs `url` or `domain`
+    pub local_storage: Vec<(String, Vec<(String, String)>)>, // (origin, [(key, value)]), e.g. "https://app.example.com"
+}
+
+/// Typed failures callers may want to tell apart; surfaced inside `anyhow::Error`.
+#[derive(Debug, Error)]
+pub enum BrowserError {
+    #[error("screenshot failed after {attempts} attempts: {message}")]
+    Screenshot { attempts: u32, message: String },
+    /// The element a locator resolved to is gone, e.g. detached by a re-render.
+    #[error("element {reason}: {locator}")]
+    ElementGone { locator: String, reason: &'static str },
+    #[error("headful Chromium needs a display but neither DISPLAY nor WAYLAND_DISPLAY is set; run under xvfb (e.g. `xvfb-run cargo run ...`) or set `BrowserConfig::headless = true`")]
+    NoDisplay,
+}
+
+impl BrowserError {
+    fn gone(target: &Locator, reason: &'static str) -> Self {
+        BrowserError::ElementGone { locator: format!("{:?}", target), reason }
+    }
+}
+
+/// How long the page is given to settle after an action, before the next snapshot.
+#[derive(Clone, Copy, Debug)]
+pub struct SettleConfig {
+    pub network_idle: Duration,         // quiet window required after navigation, submit or click
+    pub network_idle_timeout: Duration, // give up waiting for quiet after this long
+    pub interaction_delay: Duration,    // fixed pause after hover, scroll, typing and keys
+}
+
+impl Default for SettleConfig {
+    fn default() -> Self {
+        Self {
+            network_idle: Duration::from_millis(500),
+            network_idle_timeout: Duration::from_millis(5000),
+            interaction_delay: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Which headless implementation Chromium uses when `BrowserConfig::headless` is set.
+///
+/// `New` (`--headless=new`) runs the full browser without a window, so pages render like
+/// headful Chromium and screenshots match what the model expects; it is slightly heavier
+/// to start. `Legacy` (`--headless`) is the older, lighter shell whose rendering can drift
+/// from headful (fonts, scrollbars, some APIs).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HeadlessMode {
+    #[default]
+    New,
+    Legacy,
+}
+
+/// What to do about the element under a coordinate click. The model's coordinates come from
+/// a screenshot that may be stale by the time it clicks; `Record` looks up the element at the
+/// point just before clicking and reports it (`StepLog::hit_target`), `RejectDeadZone` also
+/// fails the action instead of clicking when only `<body>` or `<html>` is there.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ClickCheck {
+    #[default]
+    Off,
+    Record,
+    RejectDeadZone,
+}
+
+/// User-Agent Client Hints exposed via `navigator.userAgentData` and `Sec-CH-UA-*` headers.
+#[derive(Clone, Debug, Default)]
+pub struct UaMetadata {
+    pub brands: Vec<(String, String)>, // (brand, version)
+    pub platform: String,              // e.g. "Windows", "macOS", "Linux"
+    pub mobile: bool,
+}
+
+impl UaMetadata {
+    fn to_cdp(&self) -> UserAgentMetadata {
+        UserAgentMetadata {
+            brands: Some(
+                self.brands
+                    .iter()
+                    .map(|(b, v)| UserAgentBrandVersion::new(b.clone(), v.clone()))
+                    .collect(),
+            ),
+            full_version_list: None,
+            platform: self.platform.clone(),
+            platform_version: String::new(),
+            architecture: String::new(),
+            model: String::new(),
+            mobile: self.mobile,
+            bitness: None,
+            wow64: None,
+        }
     }
 }
 
 pub struct Browser {
-    page: Page,
-    _browser: OxideBrowser,
+    page: std::sync::RwLock<Page>,
+    _browser: Option<Arc<OxideBrowser>>, // None when driving a page owned elsewhere; shared by isolated contexts
+    cfg: BrowserConfig, // capture and settle options; launch-only fields are ignored after launch
+    console: ConsoleBuffer,
+    responses: ResponseBuffer,
+    action_mark: AtomicU64, // response seq when the latest non-wait action began
+    network: NetworkTracker,
+    watchers: std::sync::Mutex<Vec<JoinHandle<()>>>, // listeners feeding the three buffers above from the current page
+    owns_process: bool,           // launched by this handle, so `close` shuts Chromium down
+    owns_page: bool,              // false for `from_page`: the page belongs to another app
+    profile_dir: Option<PathBuf>, // generated user-data-dir, removed by `close`
+    openers: std::sync::Mutex<Vec<Page>>, // tabs `follow_tabs` left for a popup, newest last
 }
 
 impl Browser {
+    /// Wrap a page whose browser is owned by the caller; its lifecycle is left untouched.
+    pub fn from_page(page: Page) -> Self {
+        let this = Self {
+            console: Default::default(),
+            responses: Default::default(),
+            action_mark: AtomicU64::new(0),
+            network: Default::default(),
+            watchers: Default::default(),
+            page: std::sync::RwLock::new(page),
+            _browser: None,
+            cfg: BrowserConfig::default(),
+            owns_process: false,
+            owns_page: false,
+            openers: Default::default(),
+            profile_dir: None,
+        };
+        this.watch(&this.page());
+        this
+    }
+
+    /// The page currently driven, e.g. to hand to a `SelectorRedactor`. With
+    /// `single_tab_mode` off this changes as `follow_tabs` moves between tabs.
+    pub fn page(&self) -> Page {
+        match self.page.read() {
+            Ok(page) => page.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Open a page in a fresh browser context (separate cookies, storage and cache) of the same
+    /// Chromium process, set up from this handle's config like a launched page. The process
+    /// stays up until every handle sharing it is dropped; the context itself lives as long as
+    /// the process.
+    pub async fn new_incognito_context(&self) -> Result<Self> {
+        let Some(browser) = &self._browser else {
+            anyhow::bail!("isolated contexts need a browser launched or connected by this handle");
+        };
+        let context_id = browser
+            .create_browser_context(CreateBrowserContextParams::default())
+            .await?;
+        let target = CreateTargetParams::builder()
+            .url("about:blank")
+            .browser_context_id(context_id)
+            .build()
+            .map_err(|e| anyhow::anyhow!(e))?;
+        let page = browser.new_page(target).await?;
+        let this = Self {
+            console: Default::default(),
+            responses: Default::default(),
+            action_mark: AtomicU64::new(0),
+            network: Default::default(),
+            watchers: Default::default(),
+            page: std::sync::RwLock::new(page),
+            _browser: Some(browser.clone()),
+            cfg: self.cfg.clone(),
+            owns_process: false,
+            owns_page: true,
+            openers: Default::default(),
+            profile_dir: None,
+        };
+        this.watch(&this.page());
+        this.configure_page().await?;
+        Ok(this)
+    }
+
     pub async fn connect(ws_url: &str) -> Result<Self> {
         let (browser, mut handler) = OxideBrowser::connect(ws_url).await?;
         tokio::spawn(async move {
@@ -49,19 +336,63 @@ impl Browser {
                     .unwrap(),
             )
             .await;
-        Ok(Self { page, _browser: browser })
+        let this = Self {
+            console: Default::default(),
+            responses: Default::default(),
+            action_mark: AtomicU64::new(0),
+            network: Default::default(),
+            watchers: Default::default(),
+            page: std::sync::RwLock::new(page),
+            _browser: Some(Arc::new(browser)),
+            cfg: BrowserConfig::default(),
+            owns_process: false,
+            owns_page: true,
+            openers: Default::default(),
+            profile_dir: None,
+        };
+        this.watch(&this.page());
+        Ok(this)
     }
 
     pub async fn launch(cfg: BrowserConfig) -> Result<Self> {
+        // Without a display chromiumoxide fails with an opaque launch error; say what to do instead
+        if !cfg.headless
+            && cfg!(target_os = "linux")
+            && std::env::var_os("DISPLAY").is_none()
+            && std::env::var_os("WAYLAND_DISPLAY").is_none()
+        {
+            return Err(BrowserError::NoDisplay.into());
+        }
         let mut builder = chromiumoxide::browser::BrowserConfig::builder();
-        if !cfg.headless {
-            builder = builder.with_head();
-        }
-        // Use a unique user data dir per run to avoid ProcessSingleton profile lock conflicts
-        // observed when Chromium is restarted rapidly or multiple instances are spawned.
-        let ts = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
-        let mut profile_dir: PathBuf = std::env::temp_dir();
-        profile_dir.push(format!("chromiumoxide-profile-{}-{}", std::process::id(), ts));
+        builder = if !cfg.headless {
+            builder.with_head()
+        } else {
+            match cfg.headless_mode {
+                HeadlessMode::New => builder.new_headless_mode(),
+                HeadlessMode::Legacy => builder,
+            }
+        };
+        if let Some(port) = cfg.remote_debugging_port {
+            builder = builder.port(port);
+        }
+        // A user-supplied --user-data-dir wins over the auto-generated one
+        let user_profile = cfg
+            .extra_args
+            .iter()
+            .find_map(|a| a.strip_prefix("--user-data-dir="))
+            .map(PathBuf::from);
+        let generated_profile = user_profile.is_none();
+        let profile_dir = match user_profile {
+            Some(dir) => dir,
+            None => {
+                // Use a unique user data dir per run to avoid ProcessSingleton profile lock conflicts
+                // observed when Chromium is restarted rapidly or multiple instances are spawned.
+                let ts = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+                let mut dir: PathBuf = std::env::temp_dir();
+                dir.push(format!("chromiumoxide-profile-{}-{}", std::process::id(), ts));
+                dir
+            }
+        };
         let _ = std::fs::create_dir_all(&profile_dir);
         // Pass Chromium flags via builder to isolate profiles and reduce interruptions
         // Prefer explicit API if available; args remain as a fallback
@@ -70,37 +401,246 @@ impl Browser {
             .arg(format!("--user-data-dir={}", profile_dir.display()))
             .arg("--no-first-run")
             .arg("--no-default-browser-check");
+        builder = builder.args(
+            cfg.extra_args
+                .iter()
+                .filter(|a| !a.starts_with("--user-data-dir="))
+                .cloned(),
+        );
         let bcfg = builder.build().map_err(|e| anyhow::anyhow!(e))?;
         let (browser, mut handler) = OxideBrowser::launch(bcfg).await?;
         tokio::spawn(async move {
             while let Some(_ev) = handler.next().await {}
         });
         let page = browser.new_page("about:blank").await?;
-        if let Some(ua) = cfg.user_agent {
-            page.set_user_agent(ua).await?;
+        let this = Self {
+            console: Default::default(),
+            responses: Default::default(),
+            action_mark: AtomicU64::new(0),
+            network: Default::default(),
+            watchers: Default::default(),
+            page: std::sync::RwLock::new(page),
+            _browser: Some(Arc::new(browser)),
+            cfg,
+            owns_process: true,
+            owns_page: true,
+            openers: Default::default(),
+            profile_dir: generated_profile.then_some(profile_dir),
+        };
+        this.watch(&this.page());
+        this.configure_page().await?;
+        Ok(this)
+    }
+
+    /// Apply the config to a page this handle opened: user agent and client hints, a non-zero
+    /// viewport and the session seed. Password masking needs no setup; captures read it from
+    /// `cfg`.
+    async fn configure_page(&self) -> Result<()> {
+        let page = self.page();
+        if self.cfg.user_agent.is_some() || self.cfg.ua_metadata.is_some() {
+            // Client hints require a UA string; keep the browser's own when none is configured
+            let ua = match &self.cfg.user_agent {
+                Some(ua) => ua.clone(),
+                None => page.user_agent().await?,
+            };
+            let mut params = SetUserAgentOverrideParams::new(ua);
+            params.user_agent_metadata = self.cfg.ua_metadata.as_ref().map(UaMetadata::to_cdp);
+            page.execute(params).await?;
         }
         // Ensure a non-zero viewport to avoid screenshot 0-width errors
-        let _ = page
-            .execute(
-                SetDeviceMetricsOverrideParams::builder()
-                    .width(1280)
-                    .height(800)
-                    .device_scale_factor(1.0)
-                    .mobile(false)
-                    .build()
-                    .unwrap(),
-            )
-            .await;
+        let metrics = SetDeviceMetricsOverrideParams::builder()
+            .width(1280)
+            .height(800)
+            .device_scale_factor(1.0)
+            .mobile(false)
+            .build()
+            .map_err(|e| anyhow::anyhow!(e))?;
+        let _ = page.execute(metrics).await;
         // no SetVisibleSize in chromiumoxide 0.7; metrics override is enough
-        Ok(Self { page, _browser: browser })
+        if let Some(seed) = &self.cfg.session_seed {
+            self.seed_session(seed).await?;
+        }
+        Ok(())
+    }
+
+    /// Shut down what this handle owns. A launched browser closes the whole Chromium process
+    /// (including isolated contexts opened from it) and removes its generated profile dir; a
+    /// connected browser or isolated context only closes its own page, and a page wrapped with
+    /// `from_page` is left open.
+    pub async fn close(&self) -> Result<()> {
+        match (&self._browser, self.owns_process) {
+            (Some(browser), true) => {
+                // The connection drops as Chromium exits, so a failed reply is expected
+                let _ = browser.execute(CloseParams::default()).await;
+            }
+            // A page handed to `from_page` belongs to the caller and stays open
+            _ if self.owns_page => self.page().close().await?,
+            _ => {}
+        }
+        if let Some(dir) = &self.profile_dir {
+            // Chromium may still be flushing files for a moment after it exits
+            for attempt in 0..5 {
+                match tokio::fs::remove_dir_all(dir).await {
+                    Ok(()) => break,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => break,
+                    Err(e) if attempt == 4 => {
+                        tracing::warn!("profile dir {} not removed: {}", dir.display(), e);
+                    }
+                    Err(_) => sleep(Duration::from_millis(200)).await,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Install cookies, then localStorage entries. Storage is per origin, so each origin is
+    /// visited to set its items; the page is left on about:blank afterwards.
+    pub async fn seed_session(&self, seed: &SessionSeed) -> Result<()> {
+        if !seed.cookies.is_empty() {
+            self.page().execute(SetCookiesParams::new(seed.cookies.clone())).await?;
+        }
+        if seed.local_storage.is_empty() {
+            return Ok(());
+        }
+        for (origin, items) in &seed.local_storage {
+            self.import_local_storage(origin, &items.iter().cloned().collect()).await?;
+        }
+        self.page().goto("about:blank").await?;
+        Ok(())
     }
 
     pub async fn goto(&self, url: &str) -> Result<()> {
-        self.page.goto(url).await?;
-        self.page.wait_for_navigation().await?;
+        self.goto_with_status(url).await?;
+        Ok(())
+    }
+
+    /// Like `goto`, but returns the HTTP status of the final (post-redirect) response when
+    /// Chromium reports one.
+    pub async fn goto_with_status(&self, url: &str) -> Result<Option<i64>> {
+        self.page().goto(url).await?;
+        let req = self.page().wait_for_navigation_response().await?;
+        Ok(req.and_then(|r| r.response.as_ref().map(|resp| resp.status)))
+    }
+
+    /// Step through session history (`delta` -1 = back, 1 = forward) and wait for the load.
+    /// A no-op when there is no entry in that direction.
+    pub async fn history_go(&self, delta: i32) -> Result<()> {
+        let before = self.url().await.unwrap_or_default();
+        let _: bool = self.eval(format!("(function() {{ history.go({delta}); return true; }})()")).await?;
+        // history.go is async and may not navigate at all; give it a moment to start
+        let _ = self.wait_for_url(|u| u != before, Duration::from_millis(2000)).await;
+        Ok(())
+    }
+
+    pub async fn reload(&self) -> Result<()> {
+        self.page().reload().await?;
         Ok(())
     }
 
+    /// Whether `window.open`/`target=_blank` get rewritten into the current tab. Never for a
+    /// page wrapped with `from_page`: its owner relies on its own tab behaviour.
+    pub fn single_tab_mode(&self) -> bool {
+        self.cfg.single_tab_mode && self.owns_page
+    }
+
+    /// False for a page wrapped with `from_page`, which this handle must not rewrite, leave
+    /// or close.
+    pub fn owns_page(&self) -> bool {
+        self.owns_page
+    }
+
+    /// Switch to the tab the current one just opened (popup, `target=_blank`), or back to the
+    /// opener when the current tab has closed itself (e.g. an OAuth popup finishing). Returns
+    /// whether the driven page changed. Console and response watching move along.
+    pub async fn follow_tabs(&self) -> Result<bool> {
+        let Some(browser) = &self._browser else { return Ok(false) };
+        let targets = browser.execute(GetTargetsParams::default()).await?.result.target_infos;
+        let current = self.page();
+        let alive = |page: &Page| targets.iter().any(|t| &t.target_id == page.target_id());
+        if !alive(&current) {
+            let opener = {
+                let mut openers = self.openers.lock().unwrap_or_else(|e| e.into_inner());
+                let mut found = None;
+                while let Some(page) = openers.pop() {
+                    if alive(&page) {
+                        found = Some(page);
+                        break;
+                    }
+                }
+                found
+            };
+            return match opener {
+                Some(page) => {
+                    self.switch_to(page).await;
+                    Ok(true)
+                }
+                None => Ok(false),
+            };
+        }
+        let Some(child) = targets
+            .iter()
+            .rev()
+            .find(|t| t.r#type == "page" && t.opener_id.as_ref() == Some(current.target_id()))
+        else {
+            return Ok(false);
+        };
+        // The handler may not have attached to a brand-new target yet
+        let mut page = None;
+        for _ in 0..10 {
+            match browser.get_page(child.target_id.clone()).await {
+                Ok(p) => {
+                    page = Some(p);
+                    break;
+                }
+                Err(_) => sleep(Duration::from_millis(100)).await,
+            }
+        }
+        let Some(page) = page else {
+            anyhow::bail!("new tab {:?} could not be attached", child.url);
+        };
+        self.openers.lock().unwrap_or_else(|e| e.into_inner()).push(current);
+        self.switch_to(page).await;
+        Ok(true)
+    }
+
+    async fn switch_to(&self, page: Page) {
+        // New tabs get the same non-zero viewport the first one was given
+        if let Ok(metrics) = SetDeviceMetricsOverrideParams::builder()
+            .width(1280)
+            .height(800)
+            .device_scale_factor(1.0)
+            .mobile(false)
+            .build()
+        {
+            let _ = page.execute(metrics).await;
+        }
+        let _ = page.bring_to_front().await;
+        // Requests the previous tab had open will never finish from this one's point of view
+        *self.network.lock().await = NetworkState::default();
+        self.watch(&page);
+        let url = page.url().await.ok().flatten();
+        tracing::info!(url = ?url, "following tab");
+        match self.page.write() {
+            Ok(mut current) => *current = page,
+            Err(poisoned) => *poisoned.into_inner() = page,
+        }
+    }
+
+    /// Feed the console, response and network buffers from `page` alone, stopping the
+    /// listeners on the previously driven page so a tab switched back to is not piped twice.
+    fn watch(&self, page: &Page) {
+        let handles = [
+            pipe_console(page, self.console.clone()),
+            pipe_responses(page, self.responses.clone()),
+            pipe_network(page, self.network.clone()),
+        ];
+        let mut watchers = self.watchers.lock().unwrap_or_else(|e| e.into_inner());
+        for old in watchers.drain(..) {
+            old.abort();
+        }
+        watchers.extend(handles.into_iter().flatten());
+    }
+
     pub async fn enable_single_tab_mode(&self) -> Result<()> {
         // Redirect window.open and target=_blank navigations into the same tab
         let js = r#"(
@@ -119,16 +659,573 @@ impl Browser {
             .expression(js)
             .build()
             .map_err(|e| anyhow::anyhow!(e))?;
-        self.page.execute(eval).await?;
+        self.page().execute(eval).await?;
+        Ok(())
+    }
+
+    /// Emulate network conditions; throughput is in bytes/sec and `-1.0` disables throttling.
+    pub async fn set_network_conditions(
+        &self,
+        offline: bool,
+        latency_ms: u64,
+        download_bps: f64,
+        upload_bps: f64,
+    ) -> Result<()> {
+        use chromiumoxide::cdp::browser_protocol::network::EmulateNetworkConditionsParams;
+        self.page()
+            .execute(EmulateNetworkConditionsParams::new(
+                offline,
+                latency_ms as f64,
+                download_bps,
+                upload_bps,
+            ))
+            .await?;
+        Ok(())
+    }
+
+    /// Full page source (`document.documentElement.outerHTML`), optionally capped to `max_bytes`.
+    pub async fn content(&self, max_bytes: Option<usize>) -> Result<String> {
+        let html: String = self
+            .eval("document.documentElement ? document.documentElement.outerHTML : ''")
+            .await?;
+        Ok(truncate_utf8(html, max_bytes))
+    }
+
+    /// Rendered text of the page (`document.body.innerText`), optionally capped to `max_bytes`.
+    pub async fn inner_text(&self, max_bytes: Option<usize>) -> Result<String> {
+        let text: String = self
+            .eval("document.body ? document.body.innerText : ''")
+            .await?;
+        Ok(truncate_utf8(text, max_bytes))
+    }
+
+    /// Navigate to `origin` unless the page is already on it; localStorage is only reachable
+    /// from a document of its own origin.
+    async fn enter_origin(&self, origin: &str) -> Result<()> {
+        let js = format!(
+            "(function(o) {{ try {{ return new URL(o).origin === location.origin; }} catch (_) {{ return false; }} }})({})",
+            serde_json::to_string(origin)?
+        );
+        if !self.eval::<bool>(js).await? {
+            self.goto(origin).await?;
+        }
+        Ok(())
+    }
+
+    /// Every localStorage entry of `origin`, e.g. to save a session for `import_local_storage`
+    /// in a later run. Navigates there first if the page is elsewhere.
+    pub async fn get_local_storage(&self, origin: &str) -> Result<HashMap<String, String>> {
+        self.enter_origin(origin).await?;
+        self.eval("Object.fromEntries(Object.keys(localStorage).map(function(k) { return [k, localStorage.getItem(k)]; }))")
+            .await
+    }
+
+    /// Set one localStorage entry of `origin`. Navigates there first if the page is elsewhere.
+    pub async fn set_local_storage(&self, origin: &str, key: &str, value: &str) -> Result<()> {
+        self.import_local_storage(origin, &HashMap::from([(key.to_string(), value.to_string())])).await
+    }
+
+    /// Set every entry of `items` on `origin`, keeping entries not in `items`.
+    pub async fn import_local_storage(&self, origin: &str, items: &HashMap<String, String>) -> Result<()> {
+        self.enter_origin(origin).await?;
+        let js = format!(
+            r#"(function(items) {{
+              for (const k of Object.keys(items)) localStorage.setItem(k, items[k]);
+              return true;
+            }})({})"#,
+            serde_json::to_string(items)?
+        );
+        let _: bool = self.eval(js).await?;
+        Ok(())
+    }
+
+    async fn eval<T: serde::de::DeserializeOwned>(&self, js: impl Into<String>) -> Result<T> {
+        let res = self.page().evaluate(js.into()).await?;
+        Ok(res.into_value()?)
+    }
+
+    /// Choose an option of a `<select>`; every provided criterion must match the option.
+    pub async fn select_option(
+        &self,
+        target: &Locator,
+        value: Option<&str>,
+        label: Option<&str>,
+        index: Option<usize>,
+    ) -> Result<()> {
+        if value.is_none() && label.is_none() && index.is_none() {
+            anyhow::bail!("select_option needs a value, label or index");
+        }
+        let body = format!(
+            r#"if (el.tagName !== 'SELECT') return 'target is not a <select>';
+              const value = {value}, label = {label}, index = {index};
+              const idx = Array.from(el.options).findIndex(function(o, i) {{
+                return (value === null || o.value === value)
+                  && (label === null || o.label.trim() === label || o.text.trim() === label)
+                  && (index === null || i === index);
+              }});
+              if (idx < 0) return 'no matching option';
+              el.selectedIndex = idx;
+              el.dispatchEvent(new Event('input', {{ bubbles: true }}));
+              el.dispatchEvent(new Event('change', {{ bubbles: true }}));
+              return '';"#,
+            value = serde_json::to_string(&value)?,
+            label = serde_json::to_string(&label)?,
+            index = serde_json::to_string(&index)?,
+        );
+        self.with_element(target, &body).await
+    }
+
+    /// Set a text input's or textarea's value in one go and fire `input` + `change`, instead of
+    /// typing it. Much faster for long forms; use typing when keystroke handlers matter.
+    pub async fn set_value(&self, target: &Locator, value: &str) -> Result<()> {
+        let body = format!(
+            r#"const proto = el.tagName === 'TEXTAREA' ? HTMLTextAreaElement.prototype
+                : el.tagName === 'INPUT' ? HTMLInputElement.prototype : null;
+              if (!proto) return 'target is not an <input> or <textarea>';
+              // The prototype setter bypasses framework value trackers (React) so they see the change
+              Object.getOwnPropertyDescriptor(proto, 'value').set.call(el, {value});
+              el.dispatchEvent(new Event('input', {{ bubbles: true }}));
+              el.dispatchEvent(new Event('change', {{ bubbles: true }}));
+              return '';"#,
+            value = serde_json::to_string(value)?,
+        );
+        self.with_element(target, &body).await
+    }
+
+    /// Focus `target`, optionally clear it, then type `text` as real text input and fire
+    /// `change`. Clearing selects the whole value (or an editable's contents) and deletes it
+    /// the way a user would, so `input` events fire; if a framework still holds a value, the
+    /// native setter empties it.
+    pub async fn type_into(&self, target: &Locator, text: &str, clear: bool) -> Result<()> {
+        self.focus_locator(target).await?;
+        if clear {
+            self.with_element(
+                target,
+                r#"if (el.isContentEditable) {
+                  const range = document.createRange();
+                  range.selectNodeContents(el);
+                  const sel = window.getSelection();
+                  sel.removeAllRanges();
+                  sel.addRange(range);
+                  document.execCommand('delete');
+                  return '';
+                }
+                const proto = el.tagName === 'TEXTAREA' ? HTMLTextAreaElement.prototype
+                  : el.tagName === 'INPUT' ? HTMLInputElement.prototype : null;
+                if (!proto) return 'target is not an <input>, <textarea> or editable element';
+                if (typeof el.select === 'function') el.select();
+                document.execCommand('delete');
+                if (el.value !== '') {
+                  Object.getOwnPropertyDescriptor(proto, 'value').set.call(el, '');
+                  el.dispatchEvent(new Event('input', { bubbles: true }));
+                }
+                return '';"#,
+            )
+            .await?;
+        }
+        self.type_text(text).await?;
+        self.with_element(target, "el.dispatchEvent(new Event('change', { bubbles: true })); return '';")
+            .await
+    }
+
+    /// Bring a checkbox or radio to `checked`, touching it only if its state differs.
+    pub async fn set_checked(&self, target: &Locator, checked: bool) -> Result<()> {
+        let body = format!(
+            r#"if (el.type !== 'checkbox' && el.type !== 'radio') return 'target is not a checkbox or radio';
+              const want = {checked};
+              if (el.checked === want) return '';
+              // A real click fires the events frameworks listen to; fall back for radios being cleared
+              el.click();
+              if (el.checked !== want) {{
+                el.checked = want;
+                el.dispatchEvent(new Event('input', {{ bubbles: true }}));
+                el.dispatchEvent(new Event('change', {{ bubbles: true }}));
+              }}
+              return '';"#
+        );
+        self.with_element(target, &body).await
+    }
+
+    /// Set files on several `<input type=file>` elements in one call. Every path must be an
+    /// existing file and every locator a file input (accepting `multiple` when given more than
+    /// one file); nothing is set unless all of them check out.
+    pub async fn set_file_inputs(&self, inputs: &[(Locator, Vec<PathBuf>)]) -> Result<()> {
+        let mut resolved = Vec::with_capacity(inputs.len());
+        for (target, files) in inputs {
+            let mut paths = Vec::with_capacity(files.len());
+            for f in files {
+                if !f.is_file() {
+                    anyhow::bail!("upload file not found: {}", f.display());
+                }
+                paths.push(std::fs::canonicalize(f)?.to_string_lossy().into_owned());
+            }
+            let js = format!(
+                r#"(function() {{
+                  const el = {loc};
+                  if (!el || el.tagName !== 'INPUT' || el.type !== 'file') return null;
+                  return {many} && !el.multiple ? null : el;
+                }})()"#,
+                loc = locator_js(target),
+                many = files.len() > 1,
+            );
+            let eval = EvaluateParams::builder()
+                .expression(js)
+                .build()
+                .map_err(|e| anyhow::anyhow!(e))?;
+            let res = self.page().evaluate_expression(eval).await?;
+            let Some(object_id) = res.object().object_id.clone() else {
+                anyhow::bail!("no file input accepting {} file(s): {:?}", files.len(), target);
+            };
+            resolved.push((object_id, paths));
+        }
+        for (object_id, files) in resolved {
+            let params = SetFileInputFilesParams::builder()
+                .files(files)
+                .object_id(object_id)
+                .build()
+                .map_err(|e| anyhow::anyhow!(e))?;
+            self.page().execute(params).await?;
+        }
         Ok(())
     }
 
+    /// Scroll the first element matching `css` to the middle of the viewport and click its
+    /// center, measured after the scroll.
+    pub async fn scroll_into_view_and_click(&self, css: &str) -> Result<()> {
+        self.click_in_view(&Locator::Css { selector: css.to_string() }).await
+    }
+
+    /// `scroll_into_view_and_click` for any locator.
+    pub async fn click_in_view(&self, target: &Locator) -> Result<()> {
+        let (x, y) = self.click_point(target).await?;
+        self.click(x.round() as i64, y.round() as i64, "left").await
+    }
+
+    /// Scroll `target` into the middle of the viewport and pick where to click it: the point at
+    /// `BrowserConfig::click_offset` within its box, or, if `elementFromPoint` says something
+    /// else (an overlay, a sibling) is on top there, the first of a few other points that hits
+    /// the element or a descendant. Falls back to the configured point if none does.
+    pub async fn click_point(&self, target: &Locator) -> Result<(f64, f64)> {
+        self.scroll_to_center(target).await?;
+        let (ox, oy) = self.cfg.click_offset;
+        let point: Option<(f64, f64, bool)> = self
+            .eval(format!(
+                r#"(function() {{
+                  const el = {el};
+                  if (!el) return null;
+                  const r = el.getBoundingClientRect();
+                  const offsets = [[{ox}, {oy}], [0.5, 0.5], [0.25, 0.5], [0.75, 0.5], [0.5, 0.25], [0.5, 0.75], [0.15, 0.15], [0.85, 0.85]];
+                  const at = (o) => [r.left + r.width * o[0], r.top + r.height * o[1]];
+                  for (const o of offsets) {{
+                    const [x, y] = at(o);
+                    const hit = document.elementFromPoint(x, y);
+                    if (hit && (hit === el || el.contains(hit))) return [x, y, true];
+                  }}
+                  const [x, y] = at(offsets[0]);
+                  return [x, y, false];
+                }})()"#,
+                el = locator_js(target),
+                ox = ox.clamp(0.0, 1.0),
+                oy = oy.clamp(0.0, 1.0),
+            ))
+            .await?;
+        let (x, y, hit) = point.ok_or_else(|| BrowserError::gone(target, "disappeared after scrolling"))?;
+        if !hit {
+            tracing::debug!(?target, "click point is covered by another element");
+        }
+        Ok((x, y))
+    }
+
+    /// Scroll the element `target` resolves to into the middle of the viewport and return its
+    /// center in viewport pixels, measured after the scroll.
+    pub async fn scroll_to_center(&self, target: &Locator) -> Result<(f64, f64)> {
+        self.with_element(target, "el.scrollIntoView({ block: 'center', inline: 'center' }); return '';")
+            .await?;
+        // Let smooth scrolling and sticky headers finish moving before measuring
+        sleep(Duration::from_millis(150)).await;
+        let center: Option<(f64, f64)> = self
+            .eval(format!(
+                r#"(function() {{
+                  const el = {el};
+                  if (!el) return null;
+                  const r = el.getBoundingClientRect();
+                  return [r.left + r.width / 2, r.top + r.height / 2];
+                }})()"#,
+                el = locator_js(target),
+            ))
+            .await?;
+        center.ok_or_else(|| BrowserError::gone(target, "disappeared after scrolling").into())
+    }
+
+    /// Tag up to `max` visible interactive elements (links, buttons, form fields, ARIA widgets)
+    /// with ref ids "1", "2", ... in document order, replacing the previous tags, and list them.
+    /// `Locator::Ref` resolves against these tags until the next call.
+    pub async fn interactive_elements(&self, max: usize) -> Result<Vec<InteractiveElement>> {
+        let js = format!(
+            r#"(function() {{
+              const attr = '{attr}';
+              document.querySelectorAll('[' + attr + ']').forEach(function(el) {{ el.removeAttribute(attr); }});
+              const sel = 'a[href], button, input:not([type=hidden]), select, textarea, summary, [contenteditable=""], [contenteditable=true], '
+                + '[role=button], [role=link], [role=checkbox], [role=radio], [role=tab], [role=menuitem], [role=option], [role=switch], [role=textbox], [role=combobox], '
+                + '[onclick], [tabindex]:not([tabindex="-1"])';
+              const implicit = {{ A: 'link', BUTTON: 'button', SELECT: 'combobox', TEXTAREA: 'textbox', SUMMARY: 'button' }};
+              const inputRole = {{ checkbox: 'checkbox', radio: 'radio', button: 'button', submit: 'button', reset: 'button', range: 'slider' }};
+              const out = [];
+              for (const el of document.querySelectorAll(sel)) {{
+                if (out.length >= {max}) break;
+                const r = el.getBoundingClientRect();
+                const style = getComputedStyle(el);
+                if (r.width <= 0 || r.height <= 0 || style.visibility === 'hidden' || style.display === 'none' || el.disabled) continue;
+                const id = String(out.length + 1);
+                el.setAttribute(attr, id);
+                const role = el.getAttribute('role')
+                  || (el.tagName === 'INPUT' ? (inputRole[el.type] || 'textbox') : implicit[el.tagName])
+                  || 'generic';
+                const name = (el.getAttribute('aria-label') || el.innerText || el.value || el.getAttribute('placeholder')
+                  || el.getAttribute('alt') || el.getAttribute('title') || '').trim().replace(/\s+/g, ' ').slice(0, 80);
+                out.push({{ id, role, name, rect: {{ x: r.left, y: r.top, width: r.width, height: r.height }} }});
+              }}
+              return out;
+            }})()"#,
+            attr = REF_ATTR,
+        );
+        self.eval(js).await
+    }
+
+    /// Up to `max` elements matching `css`, in document order. Each comes back with a unique
+    /// CSS path as its locator, its viewport rect and a short text description.
+    pub async fn query_all(&self, css: &str, max: usize) -> Result<Vec<DomNode>> {
+        let js = format!(
+            r#"(function() {{
+              const path = (el) => {{
+                const parts = [];
+                for (; el && el.nodeType === 1 && el !== document.documentElement; el = el.parentElement) {{
+                  if (el.id) {{ parts.unshift('#' + CSS.escape(el.id)); break; }}
+                  let i = 1;
+                  for (let s = el.previousElementSibling; s; s = s.previousElementSibling) if (s.tagName === el.tagName) i++;
+                  parts.unshift(el.tagName.toLowerCase() + ':nth-of-type(' + i + ')');
+                }}
+                return parts.join(' > ');
+              }};
+              return Array.from(document.querySelectorAll({sel})).slice(0, {max}).map((el) => {{
+                const r = el.getBoundingClientRect();
+                const label = (el.innerText || el.value || el.getAttribute('aria-label') || el.getAttribute('alt') || '')
+                  .trim().replace(/\s+/g, ' ').slice(0, 80);
+                return {{ selector: path(el), tag: el.tagName.toLowerCase(), label, rect: [r.left, r.top, r.width, r.height] }};
+              }});
+            }})()"#,
+            sel = serde_json::to_string(css)?,
+        );
+        #[derive(serde::Deserialize)]
+        struct Match {
+            selector: String,
+            tag: String,
+            label: String,
+            rect: [f64; 4],
+        }
+        let matches: Vec<Match> = self.eval(js).await?;
+        Ok(matches
+            .into_iter()
+            .map(|m| DomNode {
+                locator: Locator::Css { selector: m.selector },
+                description: Some(if m.label.is_empty() { m.tag } else { format!("{}: {}", m.tag, m.label) }),
+                rect: Some(DomRect { x: m.rect[0], y: m.rect[1], width: m.rect[2], height: m.rect[3] }),
+            })
+            .collect())
+    }
+
+    /// Focus the first element matching `css` via CDP `DOM.focus`.
+    pub async fn focus(&self, css: &str) -> Result<()> {
+        let el = self.page().find_element(css).await?;
+        let params = FocusParams::builder().backend_node_id(el.backend_node_id).build();
+        self.page().execute(params).await?;
+        Ok(())
+    }
+
+    /// Focus the element `target` resolves to; for locators other than CSS.
+    pub async fn focus_locator(&self, target: &Locator) -> Result<()> {
+        match target {
+            Locator::Css { selector } => self.focus(selector).await,
+            _ => self.with_element(target, "el.focus(); return '';").await,
+        }
+    }
+
+    /// Blur whatever element currently has focus.
+    pub async fn blur(&self) -> Result<()> {
+        let _: bool = self
+            .eval("(function() { const el = document.activeElement; if (el && el.blur) el.blur(); return true; })()")
+            .await?;
+        Ok(())
+    }
+
+    pub async fn exists(&self, target: &Locator) -> Result<bool> {
+        self.eval(format!("!!({})", locator_js(target))).await
+    }
+
+    /// Whether the element's rendered text contains `text`; false if it doesn't exist.
+    pub async fn text_contains(&self, target: &Locator, text: &str) -> Result<bool> {
+        let js = format!(
+            r#"(function() {{
+              const el = {el};
+              return !!el && (el.innerText || el.textContent || '').includes({text});
+            }})()"#,
+            el = locator_js(target),
+            text = serde_json::to_string(text)?,
+        );
+        self.eval(js).await
+    }
+
+    /// Rendered text (`innerText`) of the first element matching `css`.
+    pub async fn get_text(&self, css: &str) -> Result<String> {
+        let js = format!(
+            r#"(function() {{
+              const el = document.querySelector({sel});
+              return el ? [true, el.innerText || el.textContent || ''] : [false, null];
+            }})()"#,
+            sel = serde_json::to_string(css)?,
+        );
+        match self.eval::<(bool, Option<String>)>(js).await? {
+            (true, text) => Ok(text.unwrap_or_default()),
+            (false, _) => anyhow::bail!("element not found: {}", css),
+        }
+    }
+
+    /// Attribute `name` of the first element matching `css`; None if the element lacks it.
+    pub async fn get_attribute(&self, css: &str, name: &str) -> Result<Option<String>> {
+        let js = format!(
+            r#"(function() {{
+              const el = document.querySelector({sel});
+              return el ? [true, el.getAttribute({name})] : [false, null];
+            }})()"#,
+            sel = serde_json::to_string(css)?,
+            name = serde_json::to_string(name)?,
+        );
+        match self.eval::<(bool, Option<String>)>(js).await? {
+            (true, value) => Ok(value),
+            (false, _) => anyhow::bail!("element not found: {}", css),
+        }
+    }
+
+    /// Current values of the named fields in the first element matching `form_css` (usually a
+    /// `<form>`), keyed by field name. Checkboxes and radios read `"true"`/`"false"`; when several
+    /// share a name they are keyed `name=value`. Multi-selects hold a JSON array of the selected
+    /// values. Password values read `"***"` once filled, and file inputs are skipped.
+    pub async fn form_state(&self, form_css: &str) -> Result<HashMap<String, String>> {
+        let js = format!(
+            r#"(function() {{
+              const root = document.querySelector({sel});
+              if (!root) return null;
+              const fields = Array.from(root.elements || root.querySelectorAll('input, select, textarea'))
+                .filter(el => el.name && !['submit', 'button', 'reset', 'image', 'file'].includes(el.type));
+              const count = {{}};
+              for (const el of fields) count[el.name] = (count[el.name] || 0) + 1;
+              const out = {{}};
+              for (const el of fields) {{
+                if (el.type === 'checkbox' || el.type === 'radio') {{
+                  const key = count[el.name] > 1 ? el.name + '=' + el.value : el.name;
+                  out[key] = String(el.checked);
+                }} else if (el.type === 'select-multiple') {{
+                  out[el.name] = JSON.stringify(Array.from(el.selectedOptions).map(o => o.value));
+                }} else if (el.type === 'password') {{
+                  out[el.name] = el.value ? '***' : '';
+                }} else {{
+                  out[el.name] = el.value;
+                }}
+              }}
+              return out;
+            }})()"#,
+            sel = serde_json::to_string(form_css)?,
+        );
+        self.eval::<Option<HashMap<String, String>>>(js)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("element not found: {}", form_css))
+    }
+
+    /// Run `body` with the resolved element bound to `el`; a non-empty string return is an error.
+    async fn with_element(&self, target: &Locator, body: &str) -> Result<()> {
+        let js = format!(
+            r#"(function() {{
+              const el = {el};
+              if (!el) return 'element not found';
+              {body}
+            }})()"#,
+            el = locator_js(target),
+        );
+        let err: String = self.eval(js).await?;
+        if err == "element not found" {
+            return Err(BrowserError::gone(target, "not found").into());
+        }
+        if !err.is_empty() {
+            anyhow::bail!("{}: {:?}", err, target);
+        }
+        Ok(())
+    }
+
+    /// Draw a translucent box over the first element matching `css` for ~1.5s.
+    pub async fn highlight(&self, css: &str) -> Result<()> {
+        let sel = serde_json::to_string(css)?;
+        let js = format!(
+            r#"(function() {{
+              const el = document.querySelector({sel});
+              if (!el) return false;
+              const r = el.getBoundingClientRect();
+              {draw}
+              return true;
+            }})()"#,
+            draw = HIGHLIGHT_JS,
+        );
+        let found: bool = self.eval(js).await?;
+        if !found {
+            anyhow::bail!("no element matches {}", css);
+        }
+        Ok(())
+    }
+
+    /// Draw a translucent box over a viewport rect for ~1.5s.
+    pub async fn highlight_rect(&self, x: f64, y: f64, width: f64, height: f64) -> Result<()> {
+        let js = format!(
+            r#"(function() {{
+              const r = {{ left: {x}, top: {y}, width: {width}, height: {height} }};
+              {draw}
+              return true;
+            }})()"#,
+            draw = HIGHLIGHT_JS,
+        );
+        let _: bool = self.eval(js).await?;
+        Ok(())
+    }
+
+    /// Boxes `[x, y, width, height]` of every element matching any of `selectors`, in
+    /// full-page screenshot pixels (document offset, scaled by the device pixel ratio).
+    pub async fn element_rects(&self, selectors: &[String]) -> Result<Vec<[f64; 4]>> {
+        let sels = serde_json::to_string(selectors)?;
+        let js = format!(
+            r#"(function() {{
+              const dpr = window.devicePixelRatio || 1;
+              const out = [];
+              for (const sel of {sels}) {{
+                for (const el of document.querySelectorAll(sel)) {{
+                  const r = el.getBoundingClientRect();
+                  if (r.width === 0 || r.height === 0) continue;
+                  out.push([(r.left + window.scrollX) * dpr, (r.top + window.scrollY) * dpr, r.width * dpr, r.height * dpr]);
+                }}
+              }}
+              return out;
+            }})()"#
+        );
+        self.eval(js).await
+    }
+
     pub async fn url(&self) -> Result<String> {
-        Ok(self.page.url().await?.unwrap_or_default())
+        Ok(self.page().url().await?.unwrap_or_default())
+    }
+
+    pub async fn title(&self) -> Result<String> {
+        Ok(self.page().get_title().await?.unwrap_or_default())
     }
 
     pub async fn move_mouse(&self, x: i64, y: i64) -> Result<()> {
-        self.page.move_mouse(Point { x: x as f64, y: y as f64 }).await?;
+        self.page().move_mouse(Point { x: x as f64, y: y as f64 }).await?;
         Ok(())
     }
 
@@ -144,14 +1241,14 @@ impl Browser {
             .y(y as f64)
             .button(btn)
             .click_count(1);
-        self.page
+        self.page()
             .move_mouse(Point { x: x as f64, y: y as f64 })
             .await?
             .execute(
                 cmd.clone().r#type(DispatchMouseEventType::MousePressed).build().unwrap(),
             )
             .await?;
-        self.page
+        self.page()
             .execute(cmd.r#type(DispatchMouseEventType::MouseReleased).build().unwrap())
             .await?;
         Ok(())
@@ -163,14 +1260,14 @@ impl Browser {
             .y(y as f64)
             .button(MouseButton::Left)
             .click_count(2);
-        self.page
+        self.page()
             .move_mouse(Point { x: x as f64, y: y as f64 })
             .await?
             .execute(
                 cmd.clone().r#type(DispatchMouseEventType::MousePressed).build().unwrap(),
             )
             .await?;
-        self.page
+        self.page()
             .execute(cmd.r#type(DispatchMouseEventType::MouseReleased).build().unwrap())
             .await?;
         Ok(())
@@ -182,14 +1279,14 @@ impl Browser {
             .expression(script)
             .build()
             .map_err(|e| anyhow::anyhow!(e))?;
-        self.page.execute(eval).await?;
+        self.page().execute(eval).await?;
         Ok(())
     }
 
     pub async fn type_text(&self, text: &str) -> Result<()> {
         // Use CDP Input.insertText to feed active element
         use chromiumoxide::cdp::browser_protocol::input::InsertTextParams;
-        self.page
+        self.page()
             .execute(InsertTextParams { text: text.to_string() })
             .await?;
         Ok(())
@@ -209,67 +1306,383 @@ impl Browser {
             .expression(js)
             .build()
             .map_err(|e| anyhow::anyhow!(e))?;
-        self.page.execute(eval).await?;
+        self.page().execute(eval).await?;
+        Ok(())
+    }
+
+    /// Press a named key (Enter, Tab, Escape, arrows, ...) with trusted CDP key events.
+    pub async fn press_key_cdp(&self, key: &str) -> Result<()> {
+        let (key, code, vk, text) = cdp_key(key).ok_or_else(|| anyhow::anyhow!("unsupported key: {}", key))?;
+        let key = (key.to_string(), code.to_string(), vk, text.map(str::to_string));
+        self.page().execute(key_event(&key, DispatchKeyEventType::KeyDown)?).await?;
+        self.page().execute(key_event(&key, DispatchKeyEventType::KeyUp)?).await?;
+        Ok(())
+    }
+
+    /// Hold a key down for `hold`, then release it. Accepts the named keys of `press_key_cdp`
+    /// plus single letters and digits (e.g. "w" for movement in games).
+    pub async fn hold_key(&self, key: &str, hold: Duration) -> Result<()> {
+        let key = match cdp_key(key) {
+            Some((k, c, vk, t)) => (k.to_string(), c.to_string(), vk, t.map(str::to_string)),
+            None => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if c.is_ascii_alphanumeric() => {
+                        let upper = c.to_ascii_uppercase();
+                        let code = if c.is_ascii_digit() { format!("Digit{}", c) } else { format!("Key{}", upper) };
+                        (c.to_string(), code, upper as i64, Some(c.to_string()))
+                    }
+                    _ => anyhow::bail!("unsupported key: {}", key),
+                }
+            }
+        };
+        self.page().execute(key_event(&key, DispatchKeyEventType::KeyDown)?).await?;
+        sleep(hold).await;
+        self.page().execute(key_event(&key, DispatchKeyEventType::KeyUp)?).await?;
         Ok(())
     }
 
+    /// Press Tab until the element `target` resolves to has focus, at most `max_presses`
+    /// times. Returns whether it was reached; focus is left wherever the last press put it.
+    pub async fn tab_to(&self, target: &Locator, max_presses: usize) -> Result<bool> {
+        let check = format!(
+            "(function() {{ const el = {}; return !!el && (document.activeElement === el || el.contains(document.activeElement)); }})()",
+            locator_js(target)
+        );
+        for _ in 0..max_presses {
+            if self.eval::<bool>(check.as_str()).await? {
+                return Ok(true);
+            }
+            self.press_key_cdp("Tab").await?;
+        }
+        self.eval::<bool>(check).await
+    }
+
+    pub async fn press_enter(&self) -> Result<()> {
+        self.press_key_cdp("Enter").await
+    }
+
     pub async fn drag_path(&self, points: &[(i64, i64)]) -> Result<()> {
         if points.is_empty() { return Ok(()); }
         let (sx, sy) = points[0];
         let down = DispatchMouseEventParams::builder()
             .x(sx as f64).y(sy as f64).button(MouseButton::Left);
-        self.page
+        self.page()
             .move_mouse(Point { x: sx as f64, y: sy as f64 }).await?
             .execute(down.clone().r#type(DispatchMouseEventType::MousePressed).build().unwrap())
             .await?;
         for &(x, y) in &points[1..] {
-            self.page
+            self.page()
                 .move_mouse(Point { x: x as f64, y: y as f64 })
                 .await?;
         }
-        self.page
+        self.page()
             .execute(down.r#type(DispatchMouseEventType::MouseReleased).build().unwrap())
             .await?;
         Ok(())
     }
 
+    /// Full-page PNG as base64. With `mask_password_fields`, password inputs are covered by
+    /// opaque boxes for the duration of the capture so their contents never reach the image.
+    /// With `annotate_elements`, interactive elements are re-tagged and drawn with numbered
+    /// boxes matching their ref ids.
     pub async fn screenshot_b64(&self) -> Result<String> {
+        if !self.cfg.annotate_elements {
+            return self.capture_masked_b64().await;
+        }
+        self.interactive_elements(MARK_LIMIT).await?;
+        let _: usize = self.eval(MARK_ELEMENTS_JS).await?;
+        let shot = self.capture_masked_b64().await;
+        if let Err(e) = self.eval::<bool>(UNMARK_ELEMENTS_JS).await {
+            tracing::warn!("element marks removal failed: {}", e);
+        }
+        shot
+    }
+
+    async fn capture_masked_b64(&self) -> Result<String> {
+        if !self.cfg.mask_password_fields {
+            return self.capture_b64().await;
+        }
+        let _: usize = self.eval(MASK_PASSWORDS_JS).await?;
+        let shot = self.capture_b64().await;
+        if let Err(e) = self.eval::<bool>(UNMASK_PASSWORDS_JS).await {
+            tracing::warn!("password mask removal failed: {}", e);
+        }
+        shot
+    }
+
+    async fn capture_b64(&self) -> Result<String> {
         use chromiumoxide::page::ScreenshotParamsBuilder;
         let take = || async {
             self
-                .page
+                .page()
                 .screenshot(
                     ScreenshotParamsBuilder::default()
                         .full_page(true)
-                        .omit_background(true)
+                        .omit_background(self.cfg.omit_background)
                         .build(),
                 )
                 .await
         };
-        match take().await {
-            Ok(bytes) => Ok(STANDARD.encode(bytes)),
-            Err(e) => {
-                let msg = format!("{}", e);
-                if msg.contains("0 width") || msg.contains("0 height") {
-                    // Force viewport and retry once
-                    let _ = self
-                        .page
-                        .execute(
-                            SetDeviceMetricsOverrideParams::builder()
-                                .width(1280)
-                                .height(800)
-                                .device_scale_factor(1.0)
-                                .mobile(false)
-                                .build()
-                                .unwrap(),
-                        )
-                        .await;
-                    sleep(Duration::from_millis(50)).await;
-                    let bytes = take().await?;
-                    return Ok(STANDARD.encode(bytes));
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let msg = match take().await {
+                Ok(bytes) => return Ok(STANDARD.encode(bytes)),
+                Err(e) => e.to_string(),
+            };
+            // Only a page that has not laid out yet is worth retrying
+            let zero_size = msg.contains("0 width") || msg.contains("0 height");
+            if !zero_size || attempts > self.cfg.screenshot_retries {
+                return Err(BrowserError::Screenshot { attempts, message: msg }.into());
+            }
+            // Force a viewport and start from the top before trying again
+            if let Ok(metrics) = SetDeviceMetricsOverrideParams::builder()
+                .width(1280)
+                .height(800)
+                .device_scale_factor(1.0)
+                .mobile(false)
+                .build()
+            {
+                let _ = self.page().execute(metrics).await;
+            }
+            let _ = self.eval::<bool>("(function() { window.scrollTo(0, 0); return true; })()").await;
+            sleep(self.cfg.screenshot_retry_delay).await;
+        }
+    }
+
+    /// Console messages and uncaught exceptions since the last call, oldest first. Only the
+    /// most recent `CONSOLE_BUFFER_CAP` entries are kept.
+    pub async fn drain_console(&self) -> Vec<String> {
+        self.console.lock().await.drain(..).collect()
+    }
+
+    /// Sequence number the next network response will get; pass it to
+    /// `wait_for_response_since` to ignore everything received before now.
+    pub async fn response_mark(&self) -> u64 {
+        self.responses.lock().await.next_seq
+    }
+
+    /// Note that an action is starting, so a following `Action::WaitForResponse` accepts
+    /// responses it triggered but none from earlier steps (see `last_action_mark`).
+    pub async fn mark_action_start(&self) {
+        let mark = self.response_mark().await;
+        self.action_mark.store(mark, Ordering::SeqCst);
+    }
+
+    /// `response_mark` as of the latest `mark_action_start`.
+    pub fn last_action_mark(&self) -> u64 {
+        self.action_mark.load(Ordering::SeqCst)
+    }
+
+    /// Wait for a network response received after this call whose URL contains
+    /// `url_contains` and return its status.
+    pub async fn wait_for_response(&self, url_contains: &str, timeout: Duration) -> Result<u16> {
+        let since = self.response_mark().await;
+        self.wait_for_response_since(url_contains, since, timeout).await
+    }
+
+    /// Like `wait_for_response`, but a response buffered since `since` (a `response_mark`)
+    /// counts too, e.g. one that landed while the click that triggered it settled. Each
+    /// response satisfies one wait.
+    pub async fn wait_for_response_since(&self, url_contains: &str, since: u64, timeout: Duration) -> Result<u16> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            {
+                let mut log = self.responses.lock().await;
+                let found = log
+                    .entries
+                    .iter()
+                    .position(|(seq, url, _)| *seq >= since && url.contains(url_contains));
+                if let Some(i) = found {
+                    // Consume only the match; other buffered responses stay for later waits
+                    let status = log.entries[i].2;
+                    log.entries.remove(i);
+                    return Ok(status);
                 }
-                Err(anyhow::anyhow!(e))
             }
+            if Instant::now() >= deadline {
+                anyhow::bail!("no response matching {:?} within {}ms", url_contains, timeout.as_millis());
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    /// Poll the page URL until `predicate` accepts it; returns that URL, or an error once
+    /// `timeout` passes.
+    pub async fn wait_for_url(&self, predicate: impl Fn(&str) -> bool, timeout: Duration) -> Result<String> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            // Reads can fail mid-navigation; treat that as "not there yet"
+            let url = self.url().await.unwrap_or_default();
+            if predicate(&url) {
+                return Ok(url);
+            }
+            if Instant::now() >= deadline {
+                anyhow::bail!("url did not match within {}ms (last: {})", timeout.as_millis(), url);
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Poll until `target` resolves to an element, for up to `timeout`; None if it never did.
+    /// The node keeps `target` as its locator and carries the element's viewport rect.
+    pub async fn wait_for_element(&self, target: &Locator, timeout: Duration) -> Result<Option<DomNode>> {
+        let js = format!(
+            r#"(function() {{
+              const el = {el};
+              if (!el) return null;
+              const r = el.getBoundingClientRect();
+              const label = (el.innerText || el.value || el.getAttribute('aria-label') || el.getAttribute('alt') || '')
+                .trim().replace(/\s+/g, ' ').slice(0, 80);
+              return {{ tag: el.tagName.toLowerCase(), label, rect: [r.left, r.top, r.width, r.height] }};
+            }})()"#,
+            el = locator_js(target),
+        );
+        #[derive(serde::Deserialize)]
+        struct Found {
+            tag: String,
+            label: String,
+            rect: [f64; 4],
+        }
+        let deadline = Instant::now() + timeout;
+        loop {
+            // Evaluation can fail mid-navigation; treat that as "not there yet"
+            if let Ok(Some(f)) = self.eval::<Option<Found>>(js.as_str()).await {
+                return Ok(Some(DomNode {
+                    locator: target.clone(),
+                    description: Some(if f.label.is_empty() { f.tag } else { format!("{}: {}", f.tag, f.label) }),
+                    rect: Some(DomRect { x: f.rect[0], y: f.rect[1], width: f.rect[2], height: f.rect[3] }),
+                }));
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Click the first visible consent/cookie banner button matching `BrowserConfig::consent`,
+    /// searching the page and its same-origin iframes. Returns whether one was clicked.
+    pub async fn dismiss_common_banners(&self) -> Result<bool> {
+        let js = format!(
+            r#"(function(selectors, texts) {{
+              const wanted = new Set(texts.map(function(t) {{ return t.trim().toLowerCase(); }}));
+              const visible = function(el) {{
+                const r = el.getBoundingClientRect();
+                return r.width > 0 && r.height > 0 && getComputedStyle(el).visibility !== 'hidden';
+              }};
+              const docs = [document];
+              for (const f of document.querySelectorAll('iframe')) {{
+                try {{ if (f.contentDocument) docs.push(f.contentDocument); }} catch (_) {{}}
+              }}
+              for (const doc of docs) {{
+                for (const sel of selectors) {{
+                  let el = null;
+                  try {{ el = doc.querySelector(sel); }} catch (_) {{}}
+                  if (el && visible(el)) {{ el.click(); return true; }}
+                }}
+              }}
+              for (const doc of docs) {{
+                for (const el of doc.querySelectorAll('button, a, [role=button], input[type=button], input[type=submit]')) {{
+                  const text = (el.innerText || el.value || el.getAttribute('aria-label') || '').trim().replace(/\s+/g, ' ').toLowerCase();
+                  if (wanted.has(text) && visible(el)) {{ el.click(); return true; }}
+                }}
+              }}
+              return false;
+            }})({}, {})"#,
+            serde_json::to_string(&self.cfg.consent.selectors)?,
+            serde_json::to_string(&self.cfg.consent.texts)?,
+        );
+        let clicked: bool = self.eval(js).await?;
+        if clicked {
+            // Banners usually animate out; let that finish before the next capture
+            sleep(self.cfg.settle.interaction_delay).await;
+        }
+        Ok(clicked)
+    }
+
+    /// Render the current DOM to PDF via CDP `Page.printToPDF`. Chromium only supports this
+    /// in headless mode.
+    pub async fn print_to_pdf(&self, opts: PdfOptions) -> Result<Vec<u8>> {
+        let params = PrintToPdfParams::builder()
+            .landscape(opts.landscape)
+            .scale(opts.scale)
+            .print_background(opts.print_background)
+            .build();
+        Ok(self.page().pdf(params).await?)
+    }
+
+    /// Current layout viewport (`innerWidth` x `innerHeight`) in CSS pixels.
+    pub async fn viewport(&self) -> Result<(u32, u32)> {
+        self.eval("[window.innerWidth, window.innerHeight]").await
+    }
+
+    /// The page's scroll offset `(scrollX, scrollY)` in CSS pixels.
+    pub async fn scroll_position(&self) -> Result<(i64, i64)> {
+        self.eval("[Math.round(window.scrollX), Math.round(window.scrollY)]").await
+    }
+
+    /// `document.readyState`: "loading", "interactive" or "complete".
+    pub async fn ready_state(&self) -> Result<String> {
+        self.eval("document.readyState").await
+    }
+
+    pub fn settle_config(&self) -> SettleConfig {
+        self.cfg.settle
+    }
+
+    pub fn click_check(&self) -> ClickCheck {
+        self.cfg.click_check
+    }
+
+    pub fn snapshot_form(&self) -> Option<&str> {
+        self.cfg.snapshot_form.as_deref()
+    }
+
+    /// Describe the topmost element at viewport point (`x`, `y`) as `tag#id.class "text"`,
+    /// and whether it is a dead zone (`<body>`/`<html>` itself). None when the point is
+    /// outside the viewport.
+    pub async fn element_at(&self, x: i64, y: i64) -> Result<Option<(String, bool)>> {
+        self.eval(format!(
+            r#"(function() {{
+              const el = document.elementFromPoint({x}, {y});
+              if (!el) return null;
+              let desc = el.tagName.toLowerCase();
+              if (el.id) desc += '#' + el.id;
+              if (typeof el.className === 'string' && el.className.trim()) desc += '.' + el.className.trim().split(/\s+/).slice(0, 3).join('.');
+              const text = (el.getAttribute('aria-label') || el.innerText || el.value || '').trim().replace(/\s+/g, ' ').slice(0, 40);
+              if (text && el !== document.body && el !== document.documentElement) desc += ' "' + text + '"';
+              return [desc, el === document.body || el === document.documentElement];
+            }})()"#
+        ))
+        .await
+    }
+
+    /// Wait until the document has loaded and no request has been in flight for `idle`.
+    /// Requests are counted from CDP network events, so long polls or streams keep the page
+    /// busy until `timeout`; returns false if it was still busy then.
+    pub async fn wait_for_network_idle(&self, idle: Duration, timeout: Duration) -> Result<bool> {
+        let deadline = Instant::now() + timeout;
+        let mut loading_until = Instant::now();
+        loop {
+            let now = Instant::now();
+            // Evaluation fails while a navigation swaps documents; count that as activity
+            let ready: bool = self.eval("document.readyState === 'complete'").await.unwrap_or(false);
+            if !ready {
+                loading_until = now;
+            }
+            let busy_until = {
+                let net = self.network.lock().await;
+                if net.in_flight.is_empty() { net.changed.max(loading_until) } else { now }
+            };
+            if now.duration_since(busy_until) >= idle {
+                return Ok(true);
+            }
+            if now >= deadline {
+                return Ok(false);
+            }
+            sleep(Duration::from_millis(100)).await;
         }
     }
 
@@ -279,3 +1692,300 @@ impl Browser {
     }
 }
 
+impl Drop for Browser {
+    fn drop(&mut self) {
+        // The listeners hold a page handle; don't leave them running for a page nobody reads
+        for w in self.watchers.get_mut().unwrap_or_else(|e| e.into_inner()).drain(..) {
+            w.abort();
+        }
+    }
+}
+
+/// Recent console lines, filled by a background listener per page.
+type ConsoleBuffer = Arc<Mutex<VecDeque<String>>>;
+
+const CONSOLE_BUFFER_CAP: usize = 200;
+
+/// Append `page`'s console output (`console.*` calls and uncaught exceptions) to `sink` until
+/// the page goes away. Needs a Tokio runtime; without one nothing is spawned and the buffer
+/// simply stays empty.
+fn pipe_console(page: &Page, sink: ConsoleBuffer) -> Option<JoinHandle<()>> {
+    let rt = tokio::runtime::Handle::try_current().ok()?;
+    let page = page.clone();
+    Some(rt.spawn(async move {
+        let (Ok(mut logs), Ok(mut errors)) = (
+            page.event_listener::<EventConsoleApiCalled>().await,
+            page.event_listener::<EventExceptionThrown>().await,
+        ) else {
+            return;
+        };
+        loop {
+            let line = tokio::select! {
+                Some(ev) = logs.next() => {
+                    let args: Vec<String> = ev.args.iter().map(remote_to_string).collect();
+                    format!("[{}] {}", ev.r#type.as_ref(), args.join(" "))
+                }
+                Some(ev) = errors.next() => {
+                    let d = &ev.exception_details;
+                    let detail = d.exception.as_ref().map(remote_to_string).unwrap_or_default();
+                    format!("[exception] {} {}", d.text, detail).trim_end().to_string()
+                }
+                else => break,
+            };
+            let mut buf = sink.lock().await;
+            if buf.len() >= CONSOLE_BUFFER_CAP {
+                buf.pop_front();
+            }
+            buf.push_back(line);
+        }
+    }))
+}
+
+/// Network responses not yet claimed by `wait_for_response`, oldest first.
+#[derive(Debug, Default)]
+struct ResponseLog {
+    entries: VecDeque<(u64, String, u16)>, // (seq, url, status)
+    next_seq: u64,
+}
+
+type ResponseBuffer = Arc<Mutex<ResponseLog>>;
+
+const RESPONSE_BUFFER_CAP: usize = 200;
+
+/// Append `page`'s network responses to `sink` until the page goes away; like
+/// `pipe_console`, a no-op without a Tokio runtime.
+fn pipe_responses(page: &Page, sink: ResponseBuffer) -> Option<JoinHandle<()>> {
+    let rt = tokio::runtime::Handle::try_current().ok()?;
+    let page = page.clone();
+    Some(rt.spawn(async move {
+        let Ok(mut events) = page.event_listener::<EventResponseReceived>().await else { return };
+        while let Some(ev) = events.next().await {
+            let mut log = sink.lock().await;
+            if log.entries.len() >= RESPONSE_BUFFER_CAP {
+                log.entries.pop_front();
+            }
+            let seq = log.next_seq;
+            log.next_seq += 1;
+            log.entries.push_back((seq, ev.response.url.clone(), ev.response.status.clamp(0, u16::MAX as i64) as u16));
+        }
+    }))
+}
+
+/// Requests between `Network.requestWillBeSent` and `loadingFinished`/`loadingFailed`, by
+/// request id, and when that set last changed.
+#[derive(Debug)]
+struct NetworkState {
+    in_flight: HashSet<String>,
+    changed: Instant,
+}
+
+impl Default for NetworkState {
+    fn default() -> Self {
+        Self { in_flight: HashSet::new(), changed: Instant::now() }
+    }
+}
+
+type NetworkTracker = Arc<Mutex<NetworkState>>;
+
+/// Keep `sink` up to date with `page`'s in-flight requests until the page goes away; like
+/// `pipe_console`, a no-op without a Tokio runtime.
+fn pipe_network(page: &Page, sink: NetworkTracker) -> Option<JoinHandle<()>> {
+    let rt = tokio::runtime::Handle::try_current().ok()?;
+    let page = page.clone();
+    Some(rt.spawn(async move {
+        let (Ok(mut sent), Ok(mut finished), Ok(mut failed)) = (
+            page.event_listener::<EventRequestWillBeSent>().await,
+            page.event_listener::<EventLoadingFinished>().await,
+            page.event_listener::<EventLoadingFailed>().await,
+        ) else {
+            return;
+        };
+        loop {
+            // Redirects reuse the request id, so the set absorbs the repeated "sent"
+            let (id, started) = tokio::select! {
+                Some(ev) = sent.next() => (ev.request_id.inner().clone(), true),
+                Some(ev) = finished.next() => (ev.request_id.inner().clone(), false),
+                Some(ev) = failed.next() => (ev.request_id.inner().clone(), false),
+                else => break,
+            };
+            let mut state = sink.lock().await;
+            if started {
+                state.in_flight.insert(id);
+            } else {
+                state.in_flight.remove(&id);
+            }
+            state.changed = Instant::now();
+        }
+    }))
+}
+
+fn remote_to_string(o: &RemoteObject) -> String {
+    match &o.value {
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(v) => v.to_string(),
+        None => o.description.clone().unwrap_or_else(|| o.r#type.as_ref().to_string()),
+    }
+}
+
+/// (key, code, windows virtual key code, text) for keys `press_key_cdp` knows; case-insensitive.
+pub(crate) fn cdp_key(name: &str) -> Option<(&'static str, &'static str, i64, Option<&'static str>)> {
+    let k = match name.to_ascii_lowercase().as_str() {
+        "enter" | "return" => ("Enter", "Enter", 13, Some("\r")),
+        "tab" => ("Tab", "Tab", 9, None),
+        "escape" | "esc" => ("Escape", "Escape", 27, None),
+        "backspace" => ("Backspace", "Backspace", 8, None),
+        "delete" => ("Delete", "Delete", 46, None),
+        "space" | " " => (" ", "Space", 32, Some(" ")),
+        "arrowup" | "up" => ("ArrowUp", "ArrowUp", 38, None),
+        "arrowdown" | "down" => ("ArrowDown", "ArrowDown", 40, None),
+        "arrowleft" | "left" => ("ArrowLeft", "ArrowLeft", 37, None),
+        "arrowright" | "right" => ("ArrowRight", "ArrowRight", 39, None),
+        "home" => ("Home", "Home", 36, None),
+        "end" => ("End", "End", 35, None),
+        "pageup" => ("PageUp", "PageUp", 33, None),
+        "pagedown" => ("PageDown", "PageDown", 34, None),
+        _ => return None,
+    };
+    Some(k)
+}
+
+/// One CDP key event for a (key, code, windows virtual key code, text) tuple. A `KeyDown`
+/// for a key with text carries it, which also emits keypress (what triggers implicit form
+/// submission); keys without text go down as `RawKeyDown`.
+fn key_event(
+    (key, code, vk, text): &(String, String, i64, Option<String>),
+    r#type: DispatchKeyEventType,
+) -> Result<DispatchKeyEventParams> {
+    let ev = DispatchKeyEventParams::builder()
+        .key(key.clone())
+        .code(code.clone())
+        .windows_virtual_key_code(*vk);
+    let ev = match (r#type, text) {
+        (DispatchKeyEventType::KeyDown, Some(t)) => ev.r#type(DispatchKeyEventType::KeyDown).text(t.clone()),
+        (DispatchKeyEventType::KeyDown, None) => ev.r#type(DispatchKeyEventType::RawKeyDown),
+        (other, _) => ev.r#type(other),
+    };
+    ev.build().map_err(|e| anyhow::anyhow!(e))
+}
+
+/// Attribute `Browser::interactive_elements` tags elements with; `Locator::Ref` looks it up.
+const REF_ATTR: &str = "data-glass-hands-ref";
+/// Cap on elements labelled in an `annotate_elements` screenshot.
+const MARK_LIMIT: usize = 200;
+
+/// JS expression resolving a locator to the first matching element, or null.
+pub(crate) fn locator_js(locator: &Locator) -> String {
+    let q = |s: &str| serde_json::to_string(s).unwrap_or_else(|_| "\"\"".into());
+    match locator {
+        Locator::Css { selector } => format!("document.querySelector({})", q(selector)),
+        Locator::XPath { expr } => format!(
+            "document.evaluate({}, document, null, XPathResult.FIRST_ORDERED_NODE_TYPE, null).singleNodeValue",
+            q(expr)
+        ),
+        Locator::Id { id } => format!("document.getElementById({})", q(id)),
+        // Innermost element whose text contains the pattern
+        Locator::Text { pattern } => format!(
+            r#"(function(p) {{
+              const has = function(e) {{ return (e.textContent || '').includes(p); }};
+              return Array.from(document.querySelectorAll('body *')).find(function(e) {{
+                return has(e) && !Array.from(e.children).some(has);
+              }}) || null;
+            }})({})"#,
+            q(pattern)
+        ),
+        Locator::Aria { role, name } => format!(
+            r#"(function(role, name) {{
+              const implicit = {{ A: 'link', BUTTON: 'button', SELECT: 'combobox', TEXTAREA: 'textbox', INPUT: 'textbox' }};
+              return Array.from(document.querySelectorAll('*')).find(function(e) {{
+                const r = e.getAttribute('role') || implicit[e.tagName] || '';
+                const n = (e.getAttribute('aria-label') || e.textContent || '').trim();
+                return (role === null || r === role) && (name === null || n === name);
+              }}) || null;
+            }})({}, {})"#,
+            serde_json::to_string(role).unwrap_or_else(|_| "null".into()),
+            serde_json::to_string(name).unwrap_or_else(|_| "null".into())
+        ),
+        Locator::Coordinates { x, y } => format!("document.elementFromPoint({x}, {y})"),
+        Locator::Ref { id } => format!(
+            "document.querySelector('[{}=' + JSON.stringify({}) + ']')",
+            REF_ATTR,
+            q(id)
+        ),
+    }
+}
+
+// Expects a rect-like `r` in scope. pointer-events:none keeps the overlay from eating clicks;
+// it stays long enough to show up in the post-action screenshot.
+const HIGHLIGHT_JS: &str = r#"
+              const d = document.createElement('div');
+              d.setAttribute('data-glass-hands-highlight', '');
+              d.style.cssText = 'position:fixed;box-sizing:border-box;pointer-events:none;z-index:2147483647;'
+                + 'background:rgba(255,0,0,0.2);border:2px solid #f00;'
+                + 'left:' + r.left + 'px;top:' + r.top + 'px;width:' + r.width + 'px;height:' + r.height + 'px;';
+              (document.body || document.documentElement).appendChild(d);
+              setTimeout(function() { d.remove(); }, 1500);
+"#;
+
+// Absolute boxes in document coordinates so they line up in full-page captures.
+const MASK_PASSWORDS_JS: &str = r#"(function() {
+  let n = 0;
+  for (const el of document.querySelectorAll('input[type=password]')) {
+    const r = el.getBoundingClientRect();
+    if (r.width === 0 || r.height === 0) continue;
+    const d = document.createElement('div');
+    d.setAttribute('data-glass-hands-mask', '');
+    d.style.cssText = 'position:absolute;pointer-events:none;z-index:2147483647;background:#000;'
+      + 'left:' + (r.left + window.scrollX) + 'px;top:' + (r.top + window.scrollY) + 'px;'
+      + 'width:' + r.width + 'px;height:' + r.height + 'px;';
+    document.documentElement.appendChild(d);
+    n++;
+  }
+  return n;
+})()"#;
+
+const UNMASK_PASSWORDS_JS: &str = r#"(function() {
+  document.querySelectorAll('[data-glass-hands-mask]').forEach(function(d) { d.remove(); });
+  return true;
+})()"#;
+
+/// Set-of-marks overlay: an outlined box and a numbered tag per element tagged by
+/// `interactive_elements`, positioned in document coordinates for full-page captures.
+const MARK_ELEMENTS_JS: &str = r#"(function() {
+  let n = 0;
+  for (const el of document.querySelectorAll('[data-glass-hands-ref]')) {
+    const r = el.getBoundingClientRect();
+    if (r.width === 0 || r.height === 0) continue;
+    const left = r.left + window.scrollX, top = r.top + window.scrollY;
+    const box = document.createElement('div');
+    box.setAttribute('data-glass-hands-mark', '');
+    box.style.cssText = 'position:absolute;pointer-events:none;z-index:2147483646;box-sizing:border-box;'
+      + 'border:2px solid #e6194b;left:' + left + 'px;top:' + top + 'px;width:' + r.width + 'px;height:' + r.height + 'px;';
+    const tag = document.createElement('div');
+    tag.setAttribute('data-glass-hands-mark', '');
+    tag.textContent = el.getAttribute('data-glass-hands-ref');
+    tag.style.cssText = 'position:absolute;pointer-events:none;z-index:2147483646;background:#e6194b;color:#fff;'
+      + 'font:bold 12px/14px monospace;padding:0 3px;left:' + left + 'px;top:' + Math.max(0, top - 14) + 'px;';
+    document.documentElement.appendChild(box);
+    document.documentElement.appendChild(tag);
+    n++;
+  }
+  return n;
+})()"#;
+
+const UNMARK_ELEMENTS_JS: &str = r#"(function() {
+  document.querySelectorAll('[data-glass-hands-mark]').forEach(function(d) { d.remove(); });
+  return true;
+})()"#;
+
+fn truncate_utf8(mut s: String, max_bytes: Option<usize>) -> String {
+    if let Some(max) = max_bytes {
+        if s.len() > max {
+            let mut end = max;
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+            s.truncate(end);
+        }
+    }
+    s
+}
@@ -11,20 +11,90 @@ pub struct CuaConfig {
     pub model: String,         // e.g. "computer-use-preview"
     pub tool_display: (u32, u32),
     pub environment: String,   // "browser"
+    pub truncation: String,    // "auto" or "disabled"
+    pub reasoning: Option<ReasoningConfig>,
+    pub responses_path: String,               // appended to api_base, e.g. "/responses"
+    pub extra_query: Vec<(String, String)>,   // e.g. [("api-version", "2025-03-01-preview")]
+    pub extra_headers: Vec<(String, String)>, // sent on every request, e.g. gateway routing headers
+    pub auth_style: AuthStyle,
+}
+
+/// How the API key is sent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuthStyle {
+    /// `Authorization: Bearer <key>` (OpenAI)
+    #[default]
+    Bearer,
+    /// `api-key: <key>` (Azure OpenAI)
+    ApiKeyHeader,
+}
+
+/// Optional `reasoning` block of a Responses API request.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ReasoningConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effort: Option<String>,  // e.g. "low", "medium", "high"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>, // e.g. "auto", "concise", "detailed"
 }
 
 impl Default for CuaConfig {
     fn default() -> Self {
+        Self::from_env_prefixed("")
+    }
+}
+
+impl CuaConfig {
+    /// Defaults, with the API base, key and model read from `{prefix}OPENAI_BASE_URL`,
+    /// `{prefix}OPENAI_API_KEY` and `{prefix}OPENAI_CUA_MODEL`, e.g. `AGENTX_OPENAI_API_KEY`
+    /// for prefix `"AGENTX_"`, so the agent's settings don't collide with a host app's own
+    /// OpenAI configuration. `Default` uses the unprefixed names.
+    pub fn from_env_prefixed(prefix: &str) -> Self {
+        let var = |name: &str| env::var(format!("{}{}", prefix, name));
         Self {
-            api_base: env::var("OPENAI_BASE_URL").unwrap_or_else(|_| "https://api.openai.com/v1".into()),
-            api_key: env::var("OPENAI_API_KEY").unwrap_or_default(),
-            model: env::var("OPENAI_CUA_MODEL").unwrap_or_else(|_| "computer-use-preview".into()),
+            api_base: var("OPENAI_BASE_URL").unwrap_or_else(|_| "https://api.openai.com/v1".into()),
+            api_key: var("OPENAI_API_KEY").unwrap_or_default(),
+            model: var("OPENAI_CUA_MODEL").unwrap_or_else(|_| "computer-use-preview".into()),
             tool_display: (1280, 800),
             environment: "browser".into(),
+            truncation: "auto".into(),
+            reasoning: None,
+            responses_path: "/responses".into(),
+            extra_query: Vec::new(),
+            extra_headers: Vec::new(),
+            auth_style: AuthStyle::Bearer,
         }
     }
+
+    /// Check the settings a request needs, naming the field (and environment variable) to fix.
+    /// `CuaClient::new` runs this; call it at startup to fail before launching a browser.
+    pub fn validate(&self) -> Result<()> {
+        if self.api_key.trim().is_empty() {
+            bail!(
+                "CuaConfig::api_key is empty: set the OPENAI_API_KEY environment variable \
+                 (or <prefix>OPENAI_API_KEY with CuaConfig::from_env_prefixed), or set api_key directly"
+            );
+        }
+        match reqwest::Url::parse(&self.api_base) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            _ => bail!(
+                "CuaConfig::api_base is not an http(s) URL: {:?} (from OPENAI_BASE_URL if set)",
+                self.api_base
+            ),
+        }
+        if self.model.trim().is_empty() {
+            bail!("CuaConfig::model is empty: set OPENAI_CUA_MODEL or model directly");
+        }
+        if !self.responses_path.starts_with('/') {
+            bail!("CuaConfig::responses_path must start with '/': {:?}", self.responses_path);
+        }
+        Ok(())
+    }
 }
 
+/// Client for the Responses API. `reqwest::Client` is a handle to a shared connection pool, so
+/// clones of a `CuaClient` share it too: build one and clone it into each `CuaReasoner` rather
+/// than calling `new` per reasoner.
 #[derive(Clone)]
 pub struct CuaClient {
     http: Client,
@@ -36,9 +106,10 @@ pub struct ResponseId(pub String);
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct TurnInput {
-    pub instructions: String,
+    pub instructions: String, // left out of the request when empty
     pub current_url: Option<String>,
     pub extra_user_text: Option<String>,
+    pub image_base64: Option<String>, // PNG sent with the user turn, for reasoners that want the page up front
 }
 
 #[derive(Debug)]
@@ -49,9 +120,119 @@ pub enum CuaOutput {
         action: CuaAction,
         requires_screenshot: bool,
         response_id: ResponseId,
-        safety_checks: Vec<Value>,
+        safety_checks: Vec<SafetyCheck>,
     },
+    /// A response with more than one `computer_call`, in output order. Every call must be
+    /// answered (`send_computer_outputs`) before the thread can continue, so they are meant to
+    /// be executed back to back without asking the model in between.
+    Batch { calls: Vec<CuaCall>, response_id: ResponseId },
     Done { response_id: ResponseId },
+    Refusal { reason: String },
+}
+
+/// One `computer_call` of a `CuaOutput::Batch`.
+#[derive(Debug)]
+pub struct CuaCall {
+    pub call_id: String,
+    pub action: CuaAction,
+    pub requires_screenshot: bool,
+    pub safety_checks: Vec<SafetyCheck>,
+}
+
+/// The answer to one computer call: the screenshot taken after performing it.
+#[derive(Debug)]
+pub struct CallOutput {
+    pub call_id: String,
+    pub image: CuaToolImage,
+    pub acknowledged_safety_checks: Vec<SafetyCheck>,
+}
+
+/// A pending safety check on a computer call; it must be acknowledged on the next call
+/// output for the model to proceed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SafetyCheck {
+    pub id: String,
+    #[serde(default)]
+    pub code: String,    // e.g. "malicious_instructions", "irrelevant_domain"
+    #[serde(default)]
+    pub message: String,
+}
+
+/// Client-side conversation for orgs under Zero Data Retention, where `previous_response_id`
+/// is unavailable and every request must resend the context (`turn_in_history`).
+///
+/// Screenshots dominate the payload, so `max_images` bounds how many are resent: once the
+/// history holds more, whole turns are dropped oldest first (the opening turn with the goal is
+/// kept). `truncation: "auto"` is no substitute: it only drops context server-side once the
+/// model's window overflows, so until then every resent image is uploaded and billed, and with
+/// `"disabled"` an overflowing history fails the request instead.
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    items: Vec<Value>,
+    pub max_images: Option<usize>, // None resends everything
+}
+
+impl History {
+    pub fn new(max_images: Option<usize>) -> Self {
+        Self { items: Vec::new(), max_images }
+    }
+
+    /// Input items resent with the next request.
+    pub fn items(&self) -> &[Value] {
+        &self.items
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    fn trim(&mut self) {
+        let Some(max) = self.max_images else { return };
+        let images = self.items.iter().filter(|i| has_image(i)).count();
+        let mut excess = images.saturating_sub(max);
+        let mut cut = 1;
+        while cut < self.items.len() && excess > 0 {
+            if has_image(&self.items[cut]) {
+                excess -= 1;
+            }
+            cut += 1;
+        }
+        // A call output is rejected without its call, so drop outputs left orphaned by the cut
+        while self.items.get(cut).and_then(|i| i.get("type")).and_then(|t| t.as_str()) == Some("computer_call_output") {
+            cut += 1;
+        }
+        if cut > 1 {
+            self.items.drain(1..cut);
+        }
+    }
+}
+
+/// Whether a history item carries a screenshot: a call output, or a user turn with an image.
+fn has_image(item: &Value) -> bool {
+    item.get("type").and_then(|t| t.as_str()) == Some("computer_call_output")
+        || item
+            .get("content")
+            .and_then(|c| c.as_array())
+            .is_some_and(|parts| parts.iter().any(|p| p.get("type").and_then(|t| t.as_str()) == Some("input_image")))
+}
+
+/// Token counts from a response's `usage` block.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub total_tokens: u64,
+}
+
+impl TokenUsage {
+    pub fn add(&mut self, other: TokenUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.total_tokens += other.total_tokens;
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -65,7 +246,11 @@ pub enum CuaAction {
     Keypress { key: String },
     DragPath { points: Vec<(i64, i64)> },
     WaitMs { ms: i64 },
-    Unknown(String),
+    Back,
+    Forward,
+    Reload,
+    Goto { url: String },
+    Unknown(String), // raw action JSON
 }
 
 #[derive(Debug, Serialize)]
@@ -78,32 +263,80 @@ pub struct CuaToolImage {
 
 impl CuaClient {
     pub fn new(cfg: CuaConfig) -> Result<Self> {
-        if cfg.api_key.is_empty() {
-            bail!("OPENAI_API_KEY missing");
+        cfg.validate()?;
+        Self::with_http_client(cfg, Client::new())
+    }
+
+    /// Like `new`, but reuses an existing `reqwest::Client` (and its connection pool), e.g. one
+    /// shared with clients for other models or endpoints.
+    pub fn with_http_client(cfg: CuaConfig, http: Client) -> Result<Self> {
+        cfg.validate()?;
+        Ok(Self { http, cfg })
+    }
+
+    /// POST builder for the Responses endpoint with the configured auth, path, query and headers.
+    fn post(&self) -> reqwest::RequestBuilder {
+        let url = format!("{}{}", self.cfg.api_base.trim_end_matches('/'), self.cfg.responses_path);
+        let mut req = match self.cfg.auth_style {
+            AuthStyle::Bearer => self.http.post(url).bearer_auth(&self.cfg.api_key),
+            AuthStyle::ApiKeyHeader => match reqwest::header::HeaderValue::from_str(&self.cfg.api_key) {
+                // Sensitive values are redacted from Debug output, like bearer_auth does
+                Ok(mut key) => {
+                    key.set_sensitive(true);
+                    self.http.post(url).header("api-key", key)
+                }
+                Err(_) => self.http.post(url).header("api-key", &self.cfg.api_key),
+            },
+        };
+        if !self.cfg.extra_query.is_empty() {
+            req = req.query(&self.cfg.extra_query);
+        }
+        for (name, value) in &self.cfg.extra_headers {
+            req = req.header(name.as_str(), value.as_str());
         }
-        Ok(Self {
-            http: Client::new(),
-            cfg,
-        })
+        req
     }
 
     pub async fn turn(&self, input: TurnInput, previous: Option<&ResponseId>) -> Result<CuaOutput> {
-        let url = format!("{}/responses", self.cfg.api_base);
+        Ok(self.turn_with_usage(input, previous).await?.0)
+    }
+
+    /// `turn`, plus the tokens the request consumed.
+    pub async fn turn_with_usage(&self, input: TurnInput, previous: Option<&ResponseId>) -> Result<(CuaOutput, TokenUsage)> {
+        let mut req = self.turn_request(input);
+        if let Some(prev) = previous {
+            req["previous_response_id"] = Value::String(prev.0.clone());
+        }
+        // Note: For Zero Data Retention orgs, previous_response_id is not supported.
+
+        self.send(req).await
+    }
+
+    /// `turn_with_usage` for Zero Data Retention orgs, which cannot use `previous_response_id`:
+    /// `history` is resent ahead of this turn, then extended with it and the response.
+    pub async fn turn_in_history(&self, input: TurnInput, history: &mut History) -> Result<(CuaOutput, TokenUsage)> {
+        let req = self.turn_request(input);
+        self.send_in_history(req, history).await
+    }
+
+    fn turn_request(&self, input: TurnInput) -> Value {
+        let mut content = Vec::new();
+        // Empty when the instructions are already in a resent `History`
+        if !input.instructions.is_empty() {
+            content.push(json!({ "type": "input_text", "text": input.instructions }));
+        }
+        content.push(json!({ "type": "input_text", "text": format!("current_url={}", input.current_url.unwrap_or_default()) }));
+        if let Some(extra) = input.extra_user_text {
+            content.push(json!({ "type": "input_text", "text": extra }));
+        }
+        if let Some(b64) = input.image_base64 {
+            content.push(json!({ "type": "input_image", "image_url": format!("data:image/png;base64,{}", b64) }));
+        }
         let mut req = json!({
           "model": self.cfg.model,
-          "truncation": "auto",
-          "input": [
-            { "role": "user", "content": [
-                { "type": "input_text", "text": input.instructions },
-                { "type": "input_text", "text": format!("current_url={}", input.current_url.unwrap_or_default()) }
-            ]}
-          ]
+          "truncation": self.cfg.truncation,
+          "input": [{ "role": "user", "content": content }]
         });
-        if let Some(extra) = input.extra_user_text {
-            if let Some(arr) = req.pointer_mut("/input/0/content").and_then(|v| v.as_array_mut()) {
-                arr.push(json!({ "type": "input_text", "text": extra }));
-            }
-        }
 
         // Include the hosted computer use tool only for computer-use models
         let wants_computer_tool = self.cfg.model.contains("computer-use");
@@ -115,48 +348,97 @@ impl CuaClient {
                 "environment": self.cfg.environment
             }]);
         }
-        if let Some(prev) = previous {
-            req["previous_response_id"] = Value::String(prev.0.clone());
+        if let Some(reasoning) = &self.cfg.reasoning {
+            req["reasoning"] = json!(reasoning);
         }
-        // Note: For Zero Data Retention orgs, previous_response_id is not supported.
+        req
+    }
 
-        let resp = self
-            .http
-            .post(url)
-            .bearer_auth(&self.cfg.api_key)
-            .json(&Self::normalize_tools(req))
-            .send()
+    pub async fn send_computer_output(
+        &self,
+        call_id: &str,
+        image: CuaToolImage,
+        previous: Option<&ResponseId>,
+        acknowledged_safety_checks: Option<&[SafetyCheck]>,
+        extra_user_text: Option<&str>,
+    ) -> Result<CuaOutput> {
+        let out = self
+            .send_computer_output_with_usage(call_id, image, previous, acknowledged_safety_checks, extra_user_text)
             .await?;
-        let status = resp.status();
-        let text = resp.text().await?;
-        if !status.is_success() {
-            bail!("OpenAI error {}: {}", status, text);
-        }
-        let v: Value = serde_json::from_str(&text).context("failed to parse OpenAI response JSON")?;
-        Self::parse_output(v)
+        Ok(out.0)
     }
 
-    pub async fn send_computer_output(
+    /// `send_computer_output`, plus the tokens the request consumed.
+    pub async fn send_computer_output_with_usage(
         &self,
         call_id: &str,
         image: CuaToolImage,
+        previous: Option<&ResponseId>,
+        acknowledged_safety_checks: Option<&[SafetyCheck]>,
+        extra_user_text: Option<&str>,
+    ) -> Result<(CuaOutput, TokenUsage)> {
+        let output = CallOutput {
+            call_id: call_id.to_string(),
+            image,
+            acknowledged_safety_checks: acknowledged_safety_checks.map(<[SafetyCheck]>::to_vec).unwrap_or_default(),
+        };
+        self.send_computer_outputs_with_usage(vec![output], previous, extra_user_text).await
+    }
+
+    /// Answer several computer calls (a `CuaOutput::Batch`) in one request, in call order.
+    pub async fn send_computer_outputs_with_usage(
+        &self,
+        outputs: Vec<CallOutput>,
         _previous: Option<&ResponseId>,
-        acknowledged_safety_checks: Option<&[Value]>,
-    ) -> Result<CuaOutput> {
-        let url = format!("{}/responses", self.cfg.api_base);
+        extra_user_text: Option<&str>,
+    ) -> Result<(CuaOutput, TokenUsage)> {
+        let mut req = self.outputs_request(outputs, extra_user_text);
+        if let Some(prev) = _previous {
+            // Non-ZDR orgs: continue the response thread
+            req["previous_response_id"] = Value::String(prev.0.clone());
+        }
+        // Do not include previous_response_id to support Zero Data Retention orgs
+
+        self.send(req).await
+    }
+
+    /// `send_computer_outputs_with_usage` against a client-side `History` (see `turn_in_history`).
+    pub async fn send_computer_outputs_in_history(
+        &self,
+        outputs: Vec<CallOutput>,
+        extra_user_text: Option<&str>,
+        history: &mut History,
+    ) -> Result<(CuaOutput, TokenUsage)> {
+        let req = self.outputs_request(outputs, extra_user_text);
+        self.send_in_history(req, history).await
+    }
+
+    fn outputs_request(&self, outputs: Vec<CallOutput>, extra_user_text: Option<&str>) -> Value {
+        let input: Vec<Value> = outputs
+            .into_iter()
+            .map(|o| {
+                json!({
+                  "type": "computer_call_output",
+                  "call_id": o.call_id,
+                  "output": {
+                    "type": "input_image",
+                    "image_url": format!("data:{};base64,{}", o.image.mime_type, o.image.data_base64)
+                  },
+                  "acknowledged_safety_checks": o.acknowledged_safety_checks
+                })
+            })
+            .collect();
         let mut req = json!({
           "model": self.cfg.model,
-          "truncation": "auto",
-          "input": [{
-            "type": "computer_call_output",
-            "call_id": call_id,
-            "output": {
-              "type": "input_image",
-              "image_url": format!("data:{};base64,{}", image.mime_type, image.data_base64)
-            },
-            "acknowledged_safety_checks": acknowledged_safety_checks
-          }]
+          "truncation": self.cfg.truncation,
+          "input": input
         });
+        if let Some(extra) = extra_user_text {
+            // Follows the call outputs so the pending computer_calls are still answered first
+            if let Some(arr) = req["input"].as_array_mut() {
+                arr.push(json!({ "role": "user", "content": [{ "type": "input_text", "text": extra }] }));
+            }
+        }
         // Ensure the hosted tool is enabled when sending computer output
         if self.cfg.model.contains("computer-use") {
             req["tools"] = json!([{
@@ -166,16 +448,43 @@ impl CuaClient {
                 "environment": self.cfg.environment
             }]);
         }
-        if let Some(prev) = _previous {
-            // Non-ZDR orgs: continue the response thread
-            req["previous_response_id"] = Value::String(prev.0.clone());
+        if let Some(reasoning) = &self.cfg.reasoning {
+            req["reasoning"] = json!(reasoning);
         }
-        // Do not include previous_response_id to support Zero Data Retention orgs
+        req
+    }
+
+    /// Send `req` with `history` ahead of its input and nothing stored server-side; on success
+    /// the request's input and the response's output items join the history, which is then
+    /// trimmed to its image budget.
+    async fn send_in_history(&self, mut req: Value, history: &mut History) -> Result<(CuaOutput, TokenUsage)> {
+        let new_items = req["input"].as_array().cloned().unwrap_or_default();
+        let mut input = history.items.clone();
+        input.extend(new_items.iter().cloned());
+        req["input"] = Value::Array(input);
+        req["store"] = Value::Bool(false);
+        if self.cfg.reasoning.is_some() {
+            // Unstored reasoning items can only be replayed in their encrypted form
+            req["include"] = json!(["reasoning.encrypted_content"]);
+        }
+        let (v, usage) = self.send_raw(req).await?;
+        let output = v.get("output").and_then(|x| x.as_array()).cloned().unwrap_or_default();
+        let parsed = Self::parse_output(v)?;
+        history.items.extend(new_items);
+        history.items.extend(output);
+        history.trim();
+        Ok((parsed, usage))
+    }
+
+    async fn send(&self, req: Value) -> Result<(CuaOutput, TokenUsage)> {
+        let (v, usage) = self.send_raw(req).await?;
+        Ok((Self::parse_output(v)?, usage))
+    }
 
+    /// POST `req` and return the response JSON with its token usage.
+    async fn send_raw(&self, req: Value) -> Result<(Value, TokenUsage)> {
         let resp = self
-            .http
-            .post(url)
-            .bearer_auth(&self.cfg.api_key)
+            .post()
             .json(&Self::normalize_tools(req))
             .send()
             .await?;
@@ -185,7 +494,12 @@ impl CuaClient {
             bail!("OpenAI error {}: {}", status, text);
         }
         let v: Value = serde_json::from_str(&text).context("failed to parse OpenAI response JSON")?;
-        Self::parse_output(v)
+        // Missing or malformed usage counts as zero rather than failing the turn
+        let usage = v
+            .get("usage")
+            .and_then(|u| serde_json::from_value::<TokenUsage>(u.clone()).ok())
+            .unwrap_or_default();
+        Ok((v, usage))
     }
 
     fn parse_output(v: Value) -> Result<CuaOutput> {
@@ -196,6 +510,27 @@ impl CuaClient {
             .map(|s| ResponseId(s.to_string()))
             .context("missing id")?;
 
+        // A content filter stop is the API declining to continue, not a completion
+        if v.get("status").and_then(|x| x.as_str()) == Some("incomplete")
+            && v.pointer("/incomplete_details/reason").and_then(|x| x.as_str()) == Some("content_filter")
+        {
+            return Ok(CuaOutput::Refusal { reason: "content_filter".into() });
+        }
+        // Otherwise a failed or cut-short response has no usable output; don't mistake it for Done
+        match v.get("status").and_then(|x| x.as_str()) {
+            Some("failed") => bail!(
+                "OpenAI response {} failed: {}",
+                response_id.0,
+                v.get("error").unwrap_or(&Value::Null)
+            ),
+            Some("incomplete") => bail!(
+                "OpenAI response {} incomplete: {}",
+                response_id.0,
+                v.get("incomplete_details").unwrap_or(&Value::Null)
+            ),
+            _ => {}
+        }
+
         let outputs = v
             .get("output")
             .and_then(|x| x.as_array())
@@ -204,6 +539,7 @@ impl CuaClient {
 
         // Prioritize handling of computer_call over message per Responses API contract
         let mut pending_message: Option<String> = None;
+        let mut calls: Vec<CuaCall> = Vec::new();
         for o in &outputs {
             if let Some(t) = o.get("type").and_then(|x| x.as_str()) {
                 if t == "computer_call" {
@@ -225,21 +561,35 @@ impl CuaClient {
                         .transpose()?
                         .unwrap_or(CuaAction::Unknown("unknown".into()));
 
+                    // Checks without an id cannot be acknowledged, so they are dropped
                     let safety_checks = o
                         .get("pending_safety_checks")
                         .and_then(|x| x.as_array())
-                        .cloned()
+                        .map(|arr| {
+                            arr.iter()
+                                .filter_map(|c| serde_json::from_value::<SafetyCheck>(c.clone()).ok())
+                                .collect()
+                        })
                         .unwrap_or_default();
 
-                    return Ok(CuaOutput::ComputerCall {
-                        call_id,
-                        action,
-                        requires_screenshot,
-                        response_id,
-                        safety_checks,
-                    });
+                    calls.push(CuaCall { call_id, action, requires_screenshot, safety_checks });
+                    continue;
+                }
+                if !calls.is_empty() {
+                    // Calls take precedence over whatever follows them
+                    continue;
                 }
                 if t == "message" {
+                    let refusal = o
+                        .get("content")
+                        .and_then(|x| x.as_array())
+                        .and_then(|parts| {
+                            parts.iter().find(|p| p.get("type").and_then(|x| x.as_str()) == Some("refusal"))
+                        });
+                    if let Some(part) = refusal {
+                        let reason = part.get("refusal").and_then(|x| x.as_str()).unwrap_or("refused");
+                        return Ok(CuaOutput::Refusal { reason: reason.to_string() });
+                    }
                     if let Some(text) = o.pointer("/content/0/text").and_then(|x| x.as_str()) {
                         pending_message = Some(text.to_string());
                     }
@@ -249,6 +599,18 @@ impl CuaClient {
             }
         }
 
+        if calls.len() > 1 {
+            return Ok(CuaOutput::Batch { calls, response_id });
+        }
+        if let Some(call) = calls.pop() {
+            return Ok(CuaOutput::ComputerCall {
+                call_id: call.call_id,
+                action: call.action,
+                requires_screenshot: call.requires_screenshot,
+                response_id,
+                safety_checks: call.safety_checks,
+            });
+        }
         if let Some(text) = pending_message {
             return Ok(CuaOutput::Message { text });
         }
@@ -280,9 +642,8 @@ impl CuaClient {
         let kind = v
             .get("type")
             .and_then(|x| x.as_str())
-            .unwrap_or("unknown")
-            .to_string();
-        let a = match kind.as_str() {
+            .unwrap_or("unknown");
+        let a = match kind {
             "screenshot" => CuaAction::Screenshot,
             "click" => CuaAction::Click {
                 x: v.get("x").and_then(|x| x.as_i64()).unwrap_or(0),
@@ -326,9 +687,383 @@ impl CuaClient {
             "wait" | "wait_ms" => CuaAction::WaitMs {
                 ms: v.get("ms").and_then(|x| x.as_i64()).unwrap_or(300),
             },
-            _ => CuaAction::Unknown(kind),
+            "back" => CuaAction::Back,
+            "forward" => CuaAction::Forward,
+            "reload" | "refresh" => CuaAction::Reload,
+            "goto" | "navigate" => match v.get("url").and_then(|x| x.as_str()) {
+                Some(url) => CuaAction::Goto { url: url.to_string() },
+                None => CuaAction::Unknown(v.to_string()),
+            },
+            // Keep the raw payload so callers can report exactly what was unsupported
+            _ => CuaAction::Unknown(v.to_string()),
         };
         Ok(a)
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(image: bool) -> Value {
+        let mut content = vec![json!({ "type": "input_text", "text": "Goal: pay the invoice" })];
+        if image {
+            content.push(json!({ "type": "input_image", "image_url": "data:image/png;base64,AA==" }));
+        }
+        json!({ "role": "user", "content": content })
+    }
+
+    fn call(id: &str) -> Value {
+        json!({ "type": "computer_call", "call_id": id, "action": { "type": "screenshot" } })
+    }
+
+    fn output(id: &str) -> Value {
+        json!({ "type": "computer_call_output", "call_id": id, "output": { "type": "input_image", "image_url": "data:image/png;base64,AA==" } })
+    }
+
+    fn ids(h: &History) -> Vec<String> {
+        h.items()
+            .iter()
+            .map(|i| match (i["type"].as_str(), i["call_id"].as_str()) {
+                (Some("computer_call"), Some(id)) => format!("call {id}"),
+                (Some("computer_call_output"), Some(id)) => format!("out {id}"),
+                _ => "user".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn trim_without_a_budget_keeps_everything() {
+        let mut h = History::new(None);
+        h.items = vec![user(false), call("a"), output("a"), call("b"), output("b")];
+        h.trim();
+        assert_eq!(h.items().len(), 5);
+    }
+
+    #[test]
+    fn trim_drops_whole_turns_oldest_first() {
+        let mut h = History::new(Some(1));
+        h.items = vec![user(false), call("a"), output("a"), call("b"), output("b")];
+        h.trim();
+        assert_eq!(ids(&h), ["user", "call b", "out b"]);
+    }
+
+    #[test]
+    fn trim_never_leaves_an_output_without_its_call() {
+        // One batch answered with two outputs; cutting one image must take both outputs
+        let mut h = History::new(Some(2));
+        h.items = vec![user(false), call("a"), call("b"), output("a"), output("b"), call("c"), output("c")];
+        h.trim();
+        assert_eq!(ids(&h), ["user", "call c", "out c"]);
+    }
+
+    #[test]
+    fn trim_keeps_the_opening_turn_even_over_budget() {
+        let mut h = History::new(Some(0));
+        h.items = vec![user(true), call("a"), output("a")];
+        h.trim();
+        assert_eq!(ids(&h), ["user"]);
+        assert!(has_image(&h.items()[0]));
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod mock_tests {
+    use super::*;
+    use crate::agent::{Action, AgentError, CuaReasoner, CuaReasonerConfig, Goal, Locator, Memory, Reasoner, Snapshot};
+    use crate::mock::{self, MockCuaServer};
+
+    fn config(server: &MockCuaServer) -> CuaConfig {
+        CuaConfig {
+            api_base: server.base_url(),
+            api_key: "sk-test".into(),
+            model: "computer-use-preview".into(),
+            ..CuaConfig::default()
+        }
+    }
+
+    fn input() -> TurnInput {
+        TurnInput { instructions: "Open the pricing page".into(), current_url: Some("https://example.com/".into()), ..Default::default() }
+    }
+
+    fn goal() -> Goal {
+        Goal { task: "Open the pricing page".into(), constraints: vec![], success_criteria: vec![], timeout_ms: None }
+    }
+
+    fn memory() -> Memory {
+        Memory { run_id: "run".into(), ..Default::default() }
+    }
+
+    /// A snapshot carrying a stand-in screenshot; without `max_image_dimension` it is not decoded.
+    fn snapshot(image: &str) -> Snapshot {
+        Snapshot::new(Some("https://example.com/".into()), Some(image.into()))
+    }
+
+    fn reasoner(server: &MockCuaServer, cfg: CuaReasonerConfig) -> CuaReasoner {
+        let client = CuaClient::new(config(server)).expect("client");
+        CuaReasoner::with_config(client, "", cfg).without_thread_store()
+    }
+
+    #[tokio::test]
+    async fn bearer_auth_sends_authorization_header() {
+        let server = MockCuaServer::start(vec![mock::done()]).await.expect("mock");
+        let client = CuaClient::new(config(&server)).expect("client");
+        client.turn(input(), None).await.expect("turn");
+
+        let headers = server.request_headers().await;
+        assert_eq!(headers[0].get("authorization").map(String::as_str), Some("Bearer sk-test"));
+        assert!(!headers[0].contains_key("api-key"));
+    }
+
+    #[tokio::test]
+    async fn api_key_header_auth_sends_only_api_key() {
+        let server = MockCuaServer::start(vec![mock::done()]).await.expect("mock");
+        let cfg = CuaConfig {
+            auth_style: AuthStyle::ApiKeyHeader,
+            extra_headers: vec![("x-ms-region".into(), "westeurope".into())],
+            ..config(&server)
+        };
+        let client = CuaClient::new(cfg).expect("client");
+        client.turn(input(), None).await.expect("turn");
+
+        let headers = server.request_headers().await;
+        assert_eq!(headers[0].get("api-key").map(String::as_str), Some("sk-test"));
+        assert!(!headers[0].contains_key("authorization"));
+        assert_eq!(headers[0].get("x-ms-region").map(String::as_str), Some("westeurope"));
+    }
+
+    #[tokio::test]
+    async fn parses_calls_messages_and_done() {
+        let server = MockCuaServer::start(vec![
+            mock::click("c1", 10, 20),
+            mock::message("The pricing page is open."),
+            mock::done(),
+            mock::computer_calls(&[
+                ("c2", serde_json::json!({ "type": "type", "text": "hi" })),
+                ("c3", serde_json::json!({ "type": "keypress", "keys": ["ENTER"] })),
+            ]),
+        ])
+        .await
+        .expect("mock");
+        let client = CuaClient::new(config(&server)).expect("client");
+
+        match client.turn(input(), None).await.expect("click") {
+            CuaOutput::ComputerCall { call_id, action: CuaAction::Click { x, y, button }, response_id, .. } => {
+                assert_eq!((call_id.as_str(), x, y, button.as_deref()), ("c1", 10, 20, Some("left")));
+                assert_eq!(response_id.0, "resp_mock_1");
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        match client.turn(input(), None).await.expect("message") {
+            CuaOutput::Message { text } => assert_eq!(text, "The pricing page is open."),
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(matches!(client.turn(input(), None).await.expect("done"), CuaOutput::Done { .. }));
+        match client.turn(input(), None).await.expect("batch") {
+            CuaOutput::Batch { calls, .. } => {
+                let ids: Vec<&str> = calls.iter().map(|c| c.call_id.as_str()).collect();
+                assert_eq!(ids, ["c2", "c3"]);
+                assert!(matches!(&calls[0].action, CuaAction::Type { text } if text == "hi"));
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_and_incomplete_responses_are_errors() {
+        let server = MockCuaServer::start(vec![
+            serde_json::json!({ "status": "failed", "error": { "code": "server_error", "message": "boom" }, "output": [] }),
+            serde_json::json!({ "status": "incomplete", "incomplete_details": { "reason": "max_output_tokens" }, "output": [] }),
+            serde_json::json!({ "status": "incomplete", "incomplete_details": { "reason": "content_filter" }, "output": [] }),
+        ])
+        .await
+        .expect("mock");
+        let client = CuaClient::new(config(&server)).expect("client");
+
+        let failed = client.turn(input(), None).await.expect_err("failed status");
+        assert!(failed.to_string().contains("failed"), "{failed}");
+        let incomplete = client.turn(input(), None).await.expect_err("incomplete status");
+        assert!(incomplete.to_string().contains("max_output_tokens"), "{incomplete}");
+        assert!(matches!(
+            client.turn(input(), None).await.expect("content filter"),
+            CuaOutput::Refusal { reason } if reason == "content_filter"
+        ));
+    }
+
+    #[tokio::test]
+    async fn reasoner_answers_calls_with_the_next_screenshot() {
+        let server = MockCuaServer::start(vec![mock::click("c1", 10, 20), mock::message("Done.")]).await.expect("mock");
+        let r = reasoner(&server, CuaReasonerConfig::default());
+
+        let first = r.think(&goal(), &memory(), &snapshot("Zmlyc3Q="), None).await.expect("think");
+        assert!(matches!(
+            first.action,
+            Some(Action::Click { target: Locator::Coordinates { x: 10, y: 20 }, .. })
+        ));
+        let second = r.think(&goal(), &memory(), &snapshot("YWZ0ZXI="), None).await.expect("think");
+        assert_eq!(second.plan, "Done.");
+        assert!(r.success(&goal(), &snapshot("YWZ0ZXI="), &memory()).await.expect("success"));
+
+        let requests = server.requests().await;
+        assert_eq!(requests.len(), 2);
+        let output = &requests[1]["input"][0];
+        assert_eq!(output["type"], "computer_call_output");
+        assert_eq!(output["call_id"], "c1");
+        assert_eq!(output["output"]["image_url"], "data:image/png;base64,YWZ0ZXI=");
+        assert_eq!(requests[1]["previous_response_id"], "resp_mock_1");
+    }
+
+    #[tokio::test]
+    async fn reasoner_acknowledges_safety_checks() {
+        let call = mock::with_safety_check(mock::click("c1", 1, 2), "sc1", "malicious_instructions", "Check the page");
+        let server = MockCuaServer::start(vec![call, mock::done()]).await.expect("mock");
+        let r = reasoner(&server, CuaReasonerConfig::default());
+
+        r.think(&goal(), &memory(), &snapshot("Zmlyc3Q="), None).await.expect("think");
+        r.think(&goal(), &memory(), &snapshot("YWZ0ZXI="), None).await.expect("think");
+
+        let requests = server.requests().await;
+        let acked = &requests[1]["input"][0]["acknowledged_safety_checks"];
+        assert_eq!(
+            acked,
+            &serde_json::json!([{ "id": "sc1", "code": "malicious_instructions", "message": "Check the page" }])
+        );
+    }
+
+    #[tokio::test]
+    async fn reasoner_answers_a_batch_in_one_request() {
+        let batch = mock::computer_calls(&[
+            ("c1", serde_json::json!({ "type": "click", "x": 1, "y": 2 })),
+            ("c2", serde_json::json!({ "type": "click", "x": 3, "y": 4 })),
+        ]);
+        let server = MockCuaServer::start(vec![batch, mock::done()]).await.expect("mock");
+        let r = reasoner(&server, CuaReasonerConfig::default());
+
+        for image in ["MA==", "MQ==", "Mg=="] {
+            r.think(&goal(), &memory(), &snapshot(image), None).await.expect("think");
+        }
+
+        let requests = server.requests().await;
+        assert_eq!(requests.len(), 2, "the second call is handed out without a request");
+        let ids: Vec<&str> = requests[1]["input"]
+            .as_array()
+            .expect("input")
+            .iter()
+            .filter_map(|i| i["call_id"].as_str())
+            .collect();
+        assert_eq!(ids, ["c1", "c2"]);
+    }
+
+    #[tokio::test]
+    async fn reasoner_abandons_a_batch_after_a_denied_call() {
+        let batch = mock::computer_calls(&[
+            ("c1", serde_json::json!({ "type": "click", "x": 1, "y": 2 })),
+            ("c2", serde_json::json!({ "type": "click", "x": 3, "y": 4 })),
+            ("c3", serde_json::json!({ "type": "click", "x": 5, "y": 6 })),
+        ]);
+        let server = MockCuaServer::start(vec![batch, mock::done()]).await.expect("mock");
+        let r = reasoner(&server, CuaReasonerConfig::default());
+
+        r.think(&goal(), &memory(), &snapshot("MA=="), None).await.expect("think");
+        let denied = AgentError::Denied(crate::agent::Scope::BrowserNavigate);
+        let next = r.think(&goal(), &memory(), &snapshot("MQ=="), Some(&denied)).await.expect("think");
+        assert!(next.action.is_none(), "no further batch call is handed out");
+
+        let requests = server.requests().await;
+        assert_eq!(requests.len(), 2);
+        let input = requests[1]["input"].as_array().expect("input");
+        let ids: Vec<&str> = input.iter().filter_map(|i| i["call_id"].as_str()).collect();
+        assert_eq!(ids, ["c1", "c2", "c3"]);
+        let note = input.last().and_then(|i| i["content"][0]["text"].as_str()).expect("note");
+        assert!(note.contains("2 action(s)"), "{note}");
+    }
+
+    #[tokio::test]
+    async fn zdr_reasoner_resends_history_without_storing() {
+        let server = MockCuaServer::start(vec![mock::click("c1", 10, 20), mock::done()]).await.expect("mock");
+        let r = reasoner(&server, CuaReasonerConfig { zdr: true, ..Default::default() });
+
+        r.think(&goal(), &memory(), &snapshot("Zmlyc3Q="), None).await.expect("think");
+        r.think(&goal(), &memory(), &snapshot("YWZ0ZXI="), None).await.expect("think");
+
+        let requests = server.requests().await;
+        for req in &requests {
+            assert_eq!(req["store"], false);
+            assert!(req.get("previous_response_id").is_none());
+        }
+        let types: Vec<&str> = requests[1]["input"]
+            .as_array()
+            .expect("input")
+            .iter()
+            .map(|i| i["type"].as_str().or(i["role"].as_str()).unwrap_or_default())
+            .collect();
+        assert_eq!(types, ["user", "computer_call", "computer_call_output"]);
+        assert_eq!(requests[1]["input"][0], requests[0]["input"][0]);
+    }
+
+    #[tokio::test]
+    async fn zdr_reasoner_sends_instructions_once() {
+        let server = MockCuaServer::start(vec![mock::message("Working on it."), mock::done()]).await.expect("mock");
+        let cfg = CuaReasonerConfig { zdr: true, stop_on_message: false, ..Default::default() };
+        let r = reasoner(&server, cfg);
+
+        r.think(&goal(), &memory(), &snapshot("MA=="), None).await.expect("think");
+        r.think(&goal(), &memory(), &snapshot("MQ=="), None).await.expect("think");
+
+        let requests = server.requests().await;
+        let input = requests[1]["input"].as_array().expect("input");
+        assert_eq!(input[0]["content"][0]["text"], "Goal: Open the pricing page");
+        let repeat = input.last().expect("new turn");
+        assert_eq!(repeat["role"], "user");
+        assert!(repeat["content"][0]["text"].as_str().is_some_and(|t| t.starts_with("current_url=")));
+        assert_eq!(repeat["content"].as_array().map(Vec::len), Some(1));
+    }
+
+    #[tokio::test]
+    async fn confirm_success_asks_again_with_a_fresh_screenshot() {
+        let server = MockCuaServer::start(vec![
+            mock::message("Submitted the form."),
+            mock::message("NO, the confirmation banner is missing."),
+            mock::message("Submitted it again."),
+            mock::message("YES"),
+        ])
+        .await
+        .expect("mock");
+        let r = reasoner(&server, CuaReasonerConfig::default());
+
+        r.think(&goal(), &memory(), &snapshot("MA=="), None).await.expect("think");
+        assert!(r.success(&goal(), &snapshot("MA=="), &memory()).await.expect("success"));
+        assert!(!r.confirm_success(&goal(), &snapshot("MQ=="), &memory()).await.expect("confirm"));
+        assert!(!r.success(&goal(), &snapshot("MQ=="), &memory()).await.expect("success"), "completion withdrawn");
+
+        r.think(&goal(), &memory(), &snapshot("MQ=="), None).await.expect("think");
+        assert!(r.confirm_success(&goal(), &snapshot("Mg=="), &memory()).await.expect("confirm"));
+
+        let requests = server.requests().await;
+        let image = requests[1]["input"][0]["content"]
+            .as_array()
+            .expect("content")
+            .iter()
+            .find_map(|p| p["image_url"].as_str())
+            .expect("verification turn carries the screenshot");
+        assert_eq!(image, "data:image/png;base64,MQ==");
+    }
+
+    #[tokio::test]
+    async fn calls_made_while_confirming_are_handed_out_next() {
+        let server = MockCuaServer::start(vec![mock::message("Done."), mock::click("c1", 5, 6), mock::done()])
+            .await
+            .expect("mock");
+        let r = reasoner(&server, CuaReasonerConfig::default());
+
+        r.think(&goal(), &memory(), &snapshot("MA=="), None).await.expect("think");
+        assert!(!r.confirm_success(&goal(), &snapshot("MQ=="), &memory()).await.expect("confirm"));
+        let next = r.think(&goal(), &memory(), &snapshot("MQ=="), None).await.expect("think");
+        assert!(matches!(
+            next.action,
+            Some(Action::Click { target: Locator::Coordinates { x: 5, y: 6 }, .. })
+        ));
+        assert_eq!(server.requests().await.len(), 2, "the queued call needs no request");
+    }
+}
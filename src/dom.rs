@@ -0,0 +1,40 @@
+//! Element targeting types shared by the agent loop and the browser layer.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "by", rename_all = "snake_case")]
+pub enum Locator {
+    Css { selector: String },
+    XPath { expr: String },
+    Text { pattern: String },
+    Id { id: String },
+    Aria { role: Option<String>, name: Option<String> },
+    Coordinates { x: i32, y: i32 },
+    Ref { id: String }, // an element from the latest `Computer::observe`, by its `InteractiveElement::id`
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DomRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// A clickable or editable element listed by `Computer::observe`. Act on it with
+/// `Locator::Ref { id }` instead of pixel coordinates (set-of-marks).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InteractiveElement {
+    pub id: String,   // ref id, valid until the next observe
+    pub role: String, // explicit ARIA role or the tag's implicit one, e.g. "button", "link"
+    pub name: String, // accessible-ish name: aria-label, text, value or placeholder
+    pub rect: DomRect, // viewport CSS pixels at observe time
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DomNode {
+    pub locator: Locator,
+    pub description: Option<String>,
+    pub rect: Option<DomRect>,
+}
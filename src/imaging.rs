@@ -0,0 +1,208 @@
+use anyhow::Result;
+use std::io::Cursor;
+
+/// 8-bit RGBA pixels decoded from a screenshot.
+pub(crate) struct Rgba {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+pub(crate) fn decode_png(bytes: &[u8]) -> Result<Rgba> {
+    let mut decoder = png::Decoder::new(Cursor::new(bytes));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    buf.truncate(info.buffer_size());
+    let data = match info.color_type {
+        png::ColorType::Rgba => buf,
+        png::ColorType::Rgb => buf.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => buf.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => buf.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => anyhow::bail!("unexpanded indexed png"),
+    };
+    Ok(Rgba { width: info.width, height: info.height, data })
+}
+
+pub(crate) fn encode_png(img: &Rgba) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut out, img.width, img.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&img.data)?;
+    }
+    Ok(out)
+}
+
+/// Shrink so the longer side is at most `max_dim`, keeping the aspect ratio (box filter).
+/// Returns None when the image already fits.
+pub(crate) fn downscale(img: &Rgba, max_dim: u32) -> Option<Rgba> {
+    let longest = img.width.max(img.height);
+    if max_dim == 0 || longest <= max_dim {
+        return None;
+    }
+    let scale = max_dim as f64 / longest as f64;
+    let w = ((img.width as f64 * scale).round() as u32).max(1);
+    let h = ((img.height as f64 * scale).round() as u32).max(1);
+    let mut data = vec![0u8; (w * h * 4) as usize];
+    for y in 0..h {
+        let sy0 = (y as u64 * img.height as u64 / h as u64) as u32;
+        let sy1 = (((y + 1) as u64 * img.height as u64 / h as u64) as u32).max(sy0 + 1);
+        for x in 0..w {
+            let sx0 = (x as u64 * img.width as u64 / w as u64) as u32;
+            let sx1 = (((x + 1) as u64 * img.width as u64 / w as u64) as u32).max(sx0 + 1);
+            let mut acc = [0u64; 4];
+            for sy in sy0..sy1 {
+                for sx in sx0..sx1 {
+                    let i = ((sy * img.width + sx) * 4) as usize;
+                    for (a, &v) in acc.iter_mut().zip(&img.data[i..i + 4]) {
+                        *a += v as u64;
+                    }
+                }
+            }
+            let n = ((sy1 - sy0) * (sx1 - sx0)) as u64;
+            let o = ((y * w + x) * 4) as usize;
+            for (d, a) in data[o..o + 4].iter_mut().zip(acc) {
+                *d = (a / n) as u8;
+            }
+        }
+    }
+    Some(Rgba { width: w, height: h, data })
+}
+
+/// Paint an opaque black rectangle, clipped to the image bounds.
+pub(crate) fn fill_rect(img: &mut Rgba, x: f64, y: f64, width: f64, height: f64) {
+    let clamp = |v: f64, max: u32| v.max(0.0).min(max as f64) as u32;
+    let (x0, x1) = (clamp(x.floor(), img.width), clamp((x + width).ceil(), img.width));
+    let (y0, y1) = (clamp(y.floor(), img.height), clamp((y + height).ceil(), img.height));
+    for row in y0..y1 {
+        let start = ((row * img.width + x0) * 4) as usize;
+        let end = ((row * img.width + x1) * 4) as usize;
+        for px in img.data[start..end].chunks_exact_mut(4) {
+            px.copy_from_slice(&[0, 0, 0, 255]);
+        }
+    }
+}
+
+/// 64-bit difference hash: the image shrunk to 9x8 grayscale, one bit per horizontal
+/// neighbour pair. Robust to re-encoding and scaling noise, not to real layout changes.
+pub(crate) fn dhash(img: &Rgba) -> u64 {
+    // Source pixel range [start, end) covering cell `i` of `n` along a side of `len` pixels
+    let span = |i: u32, n: u32, len: u32| {
+        let start = (i as u64 * len as u64 / n as u64) as u32;
+        let end = (((i + 1) as u64 * len as u64 / n as u64) as u32).max(start + 1).min(len);
+        (start, end)
+    };
+    let mut gray = [0f64; 72];
+    for (i, g) in gray.iter_mut().enumerate() {
+        let (sx0, sx1) = span(i as u32 % 9, 9, img.width);
+        let (sy0, sy1) = span(i as u32 / 9, 8, img.height);
+        // Integer luma sums keep equal regions exactly equal; float sums drift with cell size
+        let (mut acc, mut n) = (0u64, 0u64);
+        for sy in sy0..sy1 {
+            for sx in sx0..sx1 {
+                let p = ((sy * img.width + sx) * 4) as usize;
+                acc += 299 * img.data[p] as u64 + 587 * img.data[p + 1] as u64 + 114 * img.data[p + 2] as u64;
+                n += 1;
+            }
+        }
+        *g = if n > 0 { acc as f64 / n as f64 } else { 0.0 };
+    }
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let i = y * 9 + x;
+            hash = (hash << 1) | (gray[i] < gray[i + 1]) as u64;
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(width: u32, height: u32, px: [u8; 4]) -> Rgba {
+        Rgba { width, height, data: px.repeat((width * height) as usize) }
+    }
+
+    #[test]
+    fn downscale_keeps_images_that_fit() {
+        let img = solid(100, 50, [1, 2, 3, 255]);
+        assert!(downscale(&img, 100).is_none());
+        assert!(downscale(&img, 0).is_none());
+    }
+
+    #[test]
+    fn downscale_preserves_aspect_ratio() {
+        let small = downscale(&solid(1000, 500, [9, 9, 9, 255]), 200).expect("downscaled");
+        assert_eq!((small.width, small.height), (200, 100));
+        assert_eq!(small.data.len(), 200 * 100 * 4);
+        assert!(small.data.chunks_exact(4).all(|p| p == [9, 9, 9, 255]));
+
+        let tall = downscale(&solid(3, 3000, [0, 0, 0, 255]), 100).expect("downscaled");
+        assert_eq!((tall.width, tall.height), (1, 100));
+    }
+
+    #[test]
+    fn downscale_averages_source_pixels() {
+        // Left column black, right column white: halving to 1x1 gives mid gray
+        let img = Rgba { width: 2, height: 1, data: vec![0, 0, 0, 255, 255, 255, 255, 255] };
+        let small = downscale(&img, 1).expect("downscaled");
+        assert_eq!(small.data, vec![127, 127, 127, 255]);
+    }
+
+    /// Brightness rising left to right (or falling, with `rising` false).
+    fn gradient(width: u32, height: u32, rising: bool) -> Rgba {
+        let mut data = Vec::with_capacity((width * height * 4) as usize);
+        for _ in 0..height {
+            for x in 0..width {
+                let v = (x * 255 / (width - 1)) as u8;
+                let v = if rising { v } else { 255 - v };
+                data.extend_from_slice(&[v, v, v, 255]);
+            }
+        }
+        Rgba { width, height, data }
+    }
+
+    #[test]
+    fn dhash_of_flat_and_gradient_images() {
+        assert_eq!(dhash(&solid(64, 64, [200, 10, 10, 255])), 0);
+        assert_eq!(dhash(&gradient(90, 80, true)), u64::MAX);
+        assert_eq!(dhash(&gradient(90, 80, false)), 0);
+    }
+
+    #[test]
+    fn dhash_survives_scaling_but_not_layout_changes() {
+        let big = gradient(900, 800, true);
+        let small = downscale(&big, 300).expect("downscaled");
+        assert_eq!(dhash(&big), dhash(&small));
+
+        // A dark box on the right half flips the comparisons around it
+        let mut changed = big;
+        for y in 0..800 {
+            for x in 450..900 {
+                let i = ((y * 900 + x) * 4) as usize;
+                changed.data[i..i + 3].copy_from_slice(&[0, 0, 0]);
+            }
+        }
+        assert_ne!(dhash(&changed), dhash(&small));
+    }
+
+    #[test]
+    fn dhash_handles_images_smaller_than_the_grid() {
+        // Fewer pixels than the 9x8 grid: cells reuse pixels instead of reading out of bounds
+        assert_eq!(dhash(&solid(2, 2, [5, 5, 5, 255])), 0);
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let img = Rgba { width: 2, height: 1, data: vec![10, 20, 30, 255, 40, 50, 60, 128] };
+        let back = decode_png(&encode_png(&img).expect("encode")).expect("decode");
+        assert_eq!((back.width, back.height), (2, 1));
+        assert_eq!(back.data, img.data);
+    }
+}
@@ -1,6 +1,13 @@
 pub mod agent;
+pub mod adapter;
 pub mod cua;
 pub mod browser;
+pub mod dom;
+mod imaging;
+#[cfg(feature = "vector-memory")]
+pub mod vector_memory;
+#[cfg(feature = "mock")]
+pub mod mock;
 
 pub use agent::{Agent, AgentConfig};
 pub use browser::{Browser, BrowserConfig};
@@ -0,0 +1,162 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+/// Loopback stand-in for the Responses API: answers every POST with the next canned body and
+/// records each request's JSON and headers, so `CuaClient`/`CuaReasoner` can be driven offline. Point
+/// `CuaConfig::api_base` at `base_url()`. Once the queue is empty it answers with `done()`.
+pub struct MockCuaServer {
+    addr: std::net::SocketAddr,
+    requests: Arc<Mutex<Vec<Value>>>,
+    headers: Arc<Mutex<Vec<HashMap<String, String>>>>,
+    task: JoinHandle<()>,
+}
+
+impl MockCuaServer {
+    pub async fn start(responses: Vec<Value>) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let queue = Arc::new(Mutex::new(VecDeque::from(responses)));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let headers = Arc::new(Mutex::new(Vec::new()));
+        let (q, r, h) = (queue.clone(), requests.clone(), headers.clone());
+        let task = tokio::spawn(async move {
+            let mut served = 0usize;
+            while let Ok((stream, _)) = listener.accept().await {
+                served += 1;
+                if let Err(e) = serve(stream, served, &q, &r, &h).await {
+                    tracing::warn!(error = %e, "mock CUA server failed to answer");
+                }
+            }
+        });
+        Ok(Self { addr, requests, headers, task })
+    }
+
+    /// Value for `CuaConfig::api_base`.
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Request bodies received so far, oldest first.
+    pub async fn requests(&self) -> Vec<Value> {
+        self.requests.lock().await.clone()
+    }
+
+    /// Headers of each request so far, oldest first, keyed by lowercased name.
+    pub async fn request_headers(&self) -> Vec<HashMap<String, String>> {
+        self.headers.lock().await.clone()
+    }
+}
+
+impl Drop for MockCuaServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve(
+    mut stream: TcpStream,
+    n: usize,
+    queue: &Mutex<VecDeque<Value>>,
+    requests: &Mutex<Vec<Value>>,
+    headers: &Mutex<Vec<HashMap<String, String>>>,
+) -> Result<()> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+    // Read headers, then as much body as Content-Length announces
+    let header_end = loop {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            anyhow::bail!("connection closed before headers");
+        }
+        buf.extend_from_slice(&chunk[..read]);
+        if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break i + 4;
+        }
+    };
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let fields: HashMap<String, String> = head
+        .lines()
+        .skip(1)
+        .filter_map(|l| {
+            let (name, value) = l.split_once(':')?;
+            Some((name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        })
+        .collect();
+    let len = fields.get("content-length").and_then(|v| v.parse::<usize>().ok()).unwrap_or(0);
+    while buf.len() < header_end + len {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..read]);
+    }
+    let body = &buf[header_end..buf.len().min(header_end + len)];
+    let req: Value = serde_json::from_slice(body).context("mock CUA request was not JSON")?;
+    requests.lock().await.push(req);
+    headers.lock().await.push(fields);
+
+    let mut resp = queue.lock().await.pop_front().unwrap_or_else(done);
+    if resp.get("id").is_none() {
+        resp["id"] = Value::String(format!("resp_mock_{}", n));
+    }
+    let payload = resp.to_string();
+    let out = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        payload.len(),
+        payload
+    );
+    stream.write_all(out.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+// ===== Canned responses =====
+
+/// A `computer_call` carrying `action`; the server fills in `id` if it is missing.
+pub fn computer_call(call_id: &str, action: Value) -> Value {
+    json!({
+        "status": "completed",
+        "output": [{ "type": "computer_call", "call_id": call_id, "action": action, "pending_safety_checks": [] }]
+    })
+}
+
+/// Several `computer_call`s in one response (a `CuaOutput::Batch`), in order.
+pub fn computer_calls(calls: &[(&str, Value)]) -> Value {
+    let output: Vec<Value> = calls
+        .iter()
+        .map(|(call_id, action)| {
+            json!({ "type": "computer_call", "call_id": call_id, "action": action, "pending_safety_checks": [] })
+        })
+        .collect();
+    json!({ "status": "completed", "output": output })
+}
+
+pub fn click(call_id: &str, x: i64, y: i64) -> Value {
+    computer_call(call_id, json!({ "type": "click", "x": x, "y": y, "button": "left" }))
+}
+
+/// Attach a pending safety check to the first computer call of `resp`.
+pub fn with_safety_check(mut resp: Value, id: &str, code: &str, message: &str) -> Value {
+    if let Some(checks) = resp.pointer_mut("/output/0/pending_safety_checks").and_then(|v| v.as_array_mut()) {
+        checks.push(json!({ "id": id, "code": code, "message": message }));
+    }
+    resp
+}
+
+pub fn message(text: &str) -> Value {
+    json!({
+        "status": "completed",
+        "output": [{ "type": "message", "role": "assistant", "content": [{ "type": "output_text", "text": text }] }]
+    })
+}
+
+/// A completed response with no output items.
+pub fn done() -> Value {
+    json!({ "status": "completed", "output": [] })
+}
@@ -0,0 +1,105 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use crate::agent::{AgentError, Goal, MemoryStore, RunReport, StepLog};
+
+/// Turns text into a fixed-length vector for similarity search.
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, AgentError>;
+}
+
+/// Offline bag-of-words embedder: hashes lowercase words into `dims` buckets. Crude, but
+/// needs no external service and is good enough to match notes that share vocabulary.
+pub struct HashingEmbedder {
+    pub dims: usize,
+}
+
+impl Default for HashingEmbedder {
+    fn default() -> Self {
+        Self { dims: 256 }
+    }
+}
+
+#[async_trait]
+impl Embedder for HashingEmbedder {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, AgentError> {
+        let dims = self.dims.max(1);
+        let mut v = vec![0f32; dims];
+        for word in text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()) {
+            // FNV-1a keeps buckets stable across runs and platforms
+            let mut h: u64 = 0xcbf29ce484222325;
+            for b in word.to_lowercase().bytes() {
+                h ^= b as u64;
+                h = h.wrapping_mul(0x100000001b3);
+            }
+            v[(h % dims as u64) as usize] += 1.0;
+        }
+        Ok(v)
+    }
+}
+
+/// `MemoryStore` that embeds every step's plan and outcome and answers `recall` by cosine
+/// similarity over an in-memory index.
+pub struct VectorMemoryStore {
+    embedder: Arc<dyn Embedder>,
+    entries: Mutex<Vec<(Vec<f32>, String)>>,
+}
+
+impl VectorMemoryStore {
+    pub fn new(embedder: Arc<dyn Embedder>) -> Self {
+        Self { embedder, entries: Mutex::new(Vec::new()) }
+    }
+
+    /// The `k` stored notes most similar to `query`, best first.
+    pub async fn recall(&self, query: &str, k: usize) -> Result<Vec<String>, AgentError> {
+        let q = self.embedder.embed(query).await?;
+        let entries = self.entries.lock().await;
+        let mut scored: Vec<(f32, &String)> = entries.iter().map(|(v, note)| (cosine(&q, v), note)).collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        Ok(scored.into_iter().take(k).map(|(_, note)| note.clone()).collect())
+    }
+
+    async fn remember(&self, note: String) -> Result<(), AgentError> {
+        let v = self.embedder.embed(&note).await?;
+        self.entries.lock().await.push((v, note));
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MemoryStore for VectorMemoryStore {
+    async fn write_run_start(&self, _run_id: &str, _goal: &Goal) -> Result<(), AgentError> {
+        Ok(())
+    }
+
+    async fn write_step(&self, _run_id: &str, step: &StepLog) -> Result<(), AgentError> {
+        if step.plan.trim().is_empty() && step.error.is_none() {
+            return Ok(());
+        }
+        let mut note = format!("{} [{}]", step.plan.trim(), step.result_hint);
+        if let Some(err) = &step.error {
+            note.push_str(&format!(" error: {}", err));
+        }
+        self.remember(note).await
+    }
+
+    async fn write_run_end(&self, _run_id: &str, report: &RunReport) -> Result<(), AgentError> {
+        self.remember(format!("run for \"{}\" ended {:?}", report.goal.task, report.status)).await
+    }
+
+    async fn recall(&self, query: &str, k: usize) -> Result<Vec<String>, AgentError> {
+        VectorMemoryStore::recall(self, query, k).await
+    }
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let na: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let nb: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if na == 0.0 || nb == 0.0 {
+        0.0
+    } else {
+        dot / (na * nb)
+    }
+}
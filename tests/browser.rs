@@ -0,0 +1,270 @@
+//! Integration tests against a real headless Chromium and a loopback page server. They are
+//! ignored by default; run them with `cargo test --test browser -- --ignored` on a machine
+//! with Chrome or Chromium on PATH.
+
+use glass_hands::agent::{Action, ChromiumComputer, Computer, Locator};
+use glass_hands::browser::UaMetadata;
+use std::time::Duration;
+use glass_hands::{Browser, BrowserConfig};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Serve `html` for every request on a loopback port; returns the base URL. Loopback origins
+/// count as secure contexts, which APIs such as `navigator.userAgentData` require.
+async fn serve(html: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind loopback");
+    let addr = listener.local_addr().expect("local addr");
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf).await;
+                let resp = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    html.len(),
+                    html
+                );
+                let _ = stream.write_all(resp.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            });
+        }
+    });
+    format!("http://{}/", addr)
+}
+
+#[tokio::test]
+#[ignore = "launches Chromium"]
+async fn ua_metadata_is_visible_to_the_page() {
+    let url = serve("<html><body>hints</body></html>").await;
+    let browser = Browser::launch(BrowserConfig {
+        ua_metadata: Some(UaMetadata {
+            brands: vec![("GlassBrand".into(), "42".into())],
+            platform: "Windows".into(),
+            mobile: true,
+        }),
+        ..Default::default()
+    })
+    .await
+    .expect("launch");
+    browser.goto(&url).await.expect("goto");
+
+    let data: serde_json::Value = browser
+        .page()
+        .evaluate("({ brands: navigator.userAgentData.brands, platform: navigator.userAgentData.platform, mobile: navigator.userAgentData.mobile })")
+        .await
+        .expect("evaluate")
+        .into_value()
+        .expect("json");
+    assert_eq!(data["platform"], "Windows");
+    assert_eq!(data["mobile"], true);
+    assert!(data["brands"]
+        .as_array()
+        .is_some_and(|b| b.iter().any(|v| v["brand"] == "GlassBrand" && v["version"] == "42")));
+
+    browser.close().await.expect("close");
+}
+
+#[tokio::test]
+#[ignore = "launches Chromium"]
+async fn isolated_context_keeps_the_configured_user_agent() {
+    let url = serve("<html><body>ua</body></html>").await;
+    let browser = Browser::launch(BrowserConfig {
+        user_agent: Some("GlassAgent/1.0".into()),
+        ..Default::default()
+    })
+    .await
+    .expect("launch");
+    let isolated = browser.new_incognito_context().await.expect("context");
+    isolated.goto(&url).await.expect("goto");
+
+    let ua: String = isolated
+        .page()
+        .evaluate("navigator.userAgent")
+        .await
+        .expect("evaluate")
+        .into_value()
+        .expect("string");
+    assert_eq!(ua, "GlassAgent/1.0");
+
+    browser.close().await.expect("close");
+}
+
+#[tokio::test]
+#[ignore = "launches Chromium"]
+async fn requests_fail_while_offline() {
+    let url = serve("<html><body>net</body></html>").await;
+    let browser = Browser::launch(BrowserConfig::default()).await.expect("launch");
+    browser.goto(&url).await.expect("goto");
+    let fetch = "fetch('/ping?' + Math.random()).then(() => 'ok', () => 'failed')";
+
+    let online: String = browser.page().evaluate(fetch).await.expect("evaluate").into_value().expect("string");
+    assert_eq!(online, "ok");
+
+    browser.set_network_conditions(true, 0, -1.0, -1.0).await.expect("go offline");
+    let offline: String = browser.page().evaluate(fetch).await.expect("evaluate").into_value().expect("string");
+    assert_eq!(offline, "failed");
+
+    browser.set_network_conditions(false, 0, -1.0, -1.0).await.expect("go online");
+    let back: String = browser.page().evaluate(fetch).await.expect("evaluate").into_value().expect("string");
+    assert_eq!(back, "ok");
+
+    browser.close().await.expect("close");
+}
+
+/// Submits only on a trusted Enter keydown: implicit submission is suppressed, and synthetic
+/// (script-dispatched) events have `isTrusted === false`.
+const TRUSTED_ENTER_FORM: &str = r#"<html><body>
+<form id="f"><input id="q" name="q" autofocus><input name="other"></form>
+<p id="status">idle</p>
+<script>
+  const f = document.getElementById('f');
+  f.addEventListener('submit', e => { e.preventDefault(); document.getElementById('status').textContent = 'submitted'; });
+  document.getElementById('q').addEventListener('keydown', e => {
+    if (e.key !== 'Enter') return;
+    e.preventDefault();
+    if (e.isTrusted) f.requestSubmit();
+  });
+</script>
+</body></html>"#;
+
+#[tokio::test]
+#[ignore = "launches Chromium"]
+async fn enter_key_action_submits_with_trusted_events() {
+    let url = serve(TRUSTED_ENTER_FORM).await;
+    let computer = ChromiumComputer::launch(BrowserConfig::default()).await.expect("launch");
+    computer.open_url(&url).await.expect("open");
+
+    // A script-dispatched Enter is ignored by the page
+    computer
+        .page()
+        .evaluate("document.getElementById('q').dispatchEvent(new KeyboardEvent('keydown', { key: 'Enter', bubbles: true }))")
+        .await
+        .expect("evaluate");
+    assert_eq!(computer.get_text("#status").await.expect("status"), "idle");
+
+    computer
+        .act(&Action::Key { combo: "Enter".into() }, Duration::from_secs(5))
+        .await
+        .expect("press Enter");
+    assert_eq!(computer.get_text("#status").await.expect("status"), "submitted");
+
+    computer.close().await.expect("close");
+}
+
+#[tokio::test]
+#[ignore = "launches Chromium"]
+async fn css_locators_can_be_clicked_and_hovered() {
+    let url = serve(
+        r#"<html><body>
+        <p id="status">idle</p>
+        <button id="go" style="margin-top:2000px"
+          onmouseenter="document.getElementById('status').textContent = 'hovered'"
+          onclick="document.getElementById('status').textContent = 'clicked'">Go</button>
+        </body></html>"#,
+    )
+    .await;
+    let computer = ChromiumComputer::launch(BrowserConfig::default()).await.expect("launch");
+    computer.open_url(&url).await.expect("open");
+    let go = Locator::Css { selector: "#go".into() };
+
+    computer
+        .act(&Action::Hover { target: go.clone() }, Duration::from_secs(5))
+        .await
+        .expect("hover");
+    assert_eq!(computer.get_text("#status").await.expect("status"), "hovered");
+
+    computer
+        .act(&Action::Click { target: go, button: None }, Duration::from_secs(5))
+        .await
+        .expect("click");
+    assert_eq!(computer.get_text("#status").await.expect("status"), "clicked");
+
+    computer.close().await.expect("close");
+}
+
+/// The button is swapped for a fresh copy as soon as it is scrolled to, the way a framework
+/// re-renders a list, so the node resolved for the first click attempt is detached.
+const RERENDERED_BUTTON: &str = r#"<html><body>
+<p id="status">idle</p>
+<div id="list" style="margin-top:2000px"><button id="go">Go</button></div>
+<script>
+  window.addEventListener('scroll', function () {
+    document.getElementById('go').remove();
+    setTimeout(function () {
+      const b = document.createElement('button');
+      b.id = 'go';
+      b.textContent = 'Go';
+      b.onclick = function () { document.getElementById('status').textContent = 'clicked'; };
+      document.getElementById('list').appendChild(b);
+    }, 300);
+  }, { once: true });
+</script>
+</body></html>"#;
+
+#[tokio::test]
+#[ignore = "launches Chromium"]
+async fn click_re_resolves_a_re_rendered_css_target() {
+    let url = serve(RERENDERED_BUTTON).await;
+    let computer = ChromiumComputer::launch(BrowserConfig::default()).await.expect("launch");
+    computer.open_url(&url).await.expect("open");
+
+    computer
+        .act(&Action::Click { target: Locator::Css { selector: "#go".into() }, button: None }, Duration::from_secs(5))
+        .await
+        .expect("click");
+    assert_eq!(computer.get_text("#status").await.expect("status"), "clicked");
+
+    computer.close().await.expect("close");
+}
+
+#[tokio::test]
+#[ignore = "launches Chromium"]
+async fn password_fields_are_blacked_out_in_screenshots() {
+    use base64::Engine as _;
+    let url = serve(
+        r#"<html><body style="margin:0;background:#fff">
+        <input id="pw" type="password" value="hunter2hunter2"
+          style="position:absolute;left:40px;top:40px;width:300px;height:60px;font-size:40px;background:#fff;color:#00f;border:0">
+        </body></html>"#,
+    )
+    .await;
+    let browser = Browser::launch(BrowserConfig { mask_password_fields: true, ..Default::default() })
+        .await
+        .expect("launch");
+    browser.goto(&url).await.expect("goto");
+
+    let rects = browser.element_rects(&["#pw".to_string()]).await.expect("rects");
+    let [x, y, w, h] = rects[0];
+    let png = base64::engine::general_purpose::STANDARD
+        .decode(browser.screenshot_b64().await.expect("screenshot"))
+        .expect("base64");
+    let mut decoder = png::Decoder::new(std::io::Cursor::new(png));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().expect("png header");
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).expect("png frame");
+    let channels = info.color_type.samples();
+    let pixel = |px: u32, py: u32| {
+        let i = (py * info.width + px) as usize * channels;
+        (buf[i], buf[i + 1], buf[i + 2])
+    };
+
+    for py in (y as u32 + 2)..((y + h) as u32 - 2) {
+        for px in (x as u32 + 2)..((x + w) as u32 - 2) {
+            assert_eq!(pixel(px, py), (0, 0, 0), "unmasked pixel at ({px}, {py})");
+        }
+    }
+    assert_eq!(pixel(5, 5), (255, 255, 255), "mask spilled outside the field");
+
+    // The overlay exists only for the capture
+    let left: usize = browser
+        .page()
+        .evaluate("document.querySelectorAll('[data-glass-hands-mask]').length")
+        .await
+        .expect("evaluate")
+        .into_value()
+        .expect("count");
+    assert_eq!(left, 0);
+
+    browser.close().await.expect("close");
+}